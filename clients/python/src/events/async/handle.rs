@@ -77,7 +77,7 @@ impl ActorHandle {
                 }) {
                     eprintln!("Failed to call event callback: {}", e);
                 }
-            }).await;
+            }).await.detach();
 
             Ok(())
         })
@@ -58,7 +58,7 @@ impl ActorHandle {
                     eprintln!("Failed to call event callback: {}", e);
                 }
             })
-        );
+        ).detach();
     }
 
     pub fn disconnect(&self) {
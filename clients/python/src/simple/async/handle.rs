@@ -107,7 +107,7 @@ impl ActorHandle {
                         )
                     }).ok();
                 });
-            }).await;
+            }).await.detach();
 
             Ok(())
         })
@@ -98,7 +98,7 @@ impl ActorHandle {
                     }).ok();
                 });
             })
-        );
+        ).detach();
 
         Ok(())
     }
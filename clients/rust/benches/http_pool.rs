@@ -0,0 +1,107 @@
+//! Per-action latency of stateless actions against a local server.
+//!
+//! `pooled` goes through the client, which reuses connections from its
+//! shared HTTP pool. `new_client_per_request` replays the same request with a
+//! fresh `reqwest::Client` every time, which is what the client used to do.
+//!
+//! Run with `cargo bench -p rivetkit-client --bench http_pool`.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use rivetkit_client::{Client, EncodingKind, GetOptions, TransportKind};
+use serde_json::json;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+const RESPONSE_BODY: &str = r#"{"o":1}"#;
+
+/// Minimal HTTP/1.1 server answering every request with the same action
+/// response, keeping connections alive.
+async fn start_server() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+
+    tokio::spawn(async move {
+        loop {
+            let Ok((socket, _)) = listener.accept().await else {
+                return;
+            };
+            tokio::spawn(handle_conn(socket));
+        }
+    });
+
+    endpoint
+}
+
+async fn handle_conn(socket: TcpStream) {
+    let mut socket = BufReader::new(socket);
+
+    loop {
+        // Read the request head
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            match socket.read_line(&mut line).await {
+                Ok(0) | Err(_) => return,
+                Ok(_) => {}
+            }
+            if line == "\r\n" {
+                break;
+            }
+            let lower = line.to_ascii_lowercase();
+            if let Some(value) = lower.strip_prefix("content-length:") {
+                content_length = value.trim().parse().unwrap_or(0);
+            }
+        }
+
+        let mut body = vec![0u8; content_length];
+        if socket.read_exact(&mut body).await.is_err() {
+            return;
+        }
+
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            RESPONSE_BODY.len(),
+            RESPONSE_BODY
+        );
+        if socket.get_mut().write_all(response.as_bytes()).await.is_err() {
+            return;
+        }
+    }
+}
+
+fn bench_actions(c: &mut Criterion) {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let endpoint = rt.block_on(start_server());
+
+    let mut group = c.benchmark_group("stateless_action");
+
+    let client = Client::new(&endpoint, TransportKind::WebSocket, EncodingKind::Json);
+    let handle = client.get_for_id("actor-123", GetOptions::default()).unwrap();
+    group.bench_function("pooled", |b| {
+        b.to_async(&rt)
+            .iter(|| async { handle.action("increment", vec![json!(1)]).await.unwrap() })
+    });
+
+    let url = format!("{}/actors/actions/increment", endpoint);
+    group.bench_function("new_client_per_request", |b| {
+        b.to_async(&rt).iter(|| async {
+            reqwest::Client::new()
+                .post(&url)
+                .header("X-AC-Encoding", "json")
+                .header("X-AC-Query", r#"{"getForId":{"actorId":"actor-123"}}"#)
+                .header("Content-Type", "application/json")
+                .body(r#"{"a":[1]}"#)
+                .send()
+                .await
+                .unwrap()
+                .bytes()
+                .await
+                .unwrap()
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_actions);
+criterion_main!(benches);
@@ -0,0 +1,380 @@
+use proc_macro::TokenStream;
+use proc_macro2::{Ident, Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{
+    meta::ParseNestedMeta, parse_macro_input, spanned::Spanned, Attribute, FnArg, ItemTrait, LitStr, Pat,
+    ReturnType, TraitItem, TraitItemFn, Type,
+};
+
+/// Generates a typed client for an actor from a trait definition.
+///
+/// ```ignore
+/// #[rivetkit_client::actor_client(name = "chat-room")]
+/// trait ChatRoom {
+///     async fn send_message(&self, user: String, text: String) -> rivetkit_client::Result<()>;
+///
+///     #[event]
+///     fn new_message(user: String, text: String);
+/// }
+/// ```
+///
+/// Actions are implemented for both `ActorHandle` (stateless HTTP) and
+/// `ActorConnection`. Events generate an `on_<event>` method on a companion
+/// `<Trait>Events` trait, implemented for `ActorConnection` only.
+///
+/// A `<Trait>Client` unit struct holds the actor `name` and `get` &
+/// `get_or_create` functions that look it up, e.g.
+/// `ChatRoomClient::get(&client, key, GetOptions::default())`.
+///
+/// Action & event names default to the camelCase form of the method name and
+/// can be overridden with `#[action(name = "...")]` / `#[event(name = "...")]`.
+#[proc_macro_attribute]
+pub fn actor_client(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut args = ActorClientArgs::default();
+    let parser = syn::meta::parser(|meta| args.parse(meta));
+    parse_macro_input!(attr with parser);
+
+    let item = parse_macro_input!(item as ItemTrait);
+
+    expand(args, item)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[derive(Default)]
+struct ActorClientArgs {
+    name: Option<LitStr>,
+}
+
+impl ActorClientArgs {
+    fn parse(&mut self, meta: ParseNestedMeta) -> syn::Result<()> {
+        if meta.path.is_ident("name") {
+            self.name = Some(meta.value()?.parse()?);
+            Ok(())
+        } else {
+            Err(meta.error("unsupported actor_client property"))
+        }
+    }
+}
+
+struct Action {
+    sig: TraitItemFn,
+    name: String,
+    args: Vec<Ident>,
+}
+
+struct Event {
+    method: Ident,
+    name: String,
+    arg_types: Vec<Type>,
+    attrs: Vec<Attribute>,
+}
+
+enum MethodKind {
+    Action(Option<LitStr>),
+    Event(Option<LitStr>),
+}
+
+fn expand(args: ActorClientArgs, item: ItemTrait) -> syn::Result<TokenStream2> {
+    let Some(actor_name) = args.name else {
+        return Err(syn::Error::new(
+            Span::call_site(),
+            "missing actor name, e.g. #[actor_client(name = \"counter\")]",
+        ));
+    };
+
+    if !item.generics.params.is_empty() || item.generics.where_clause.is_some() {
+        return Err(syn::Error::new(item.generics.span(), "actor client traits cannot be generic"));
+    }
+    if !item.supertraits.is_empty() {
+        return Err(syn::Error::new(
+            item.supertraits.span(),
+            "actor client traits cannot have supertraits",
+        ));
+    }
+
+    let mut actions = Vec::new();
+    let mut events = Vec::new();
+
+    for trait_item in item.items {
+        let TraitItem::Fn(mut method) = trait_item else {
+            return Err(syn::Error::new(
+                trait_item.span(),
+                "actor client traits can only contain methods",
+            ));
+        };
+
+        if let Some(default) = &method.default {
+            return Err(syn::Error::new(default.span(), "actor client methods cannot have a body"));
+        }
+
+        match take_method_kind(&mut method.attrs)? {
+            MethodKind::Action(name) => actions.push(parse_action(method, name)?),
+            MethodKind::Event(name) => events.push(parse_event(method, name)?),
+        }
+    }
+
+    let vis = &item.vis;
+    let attrs = &item.attrs;
+    let trait_ident = &item.ident;
+    let events_ident = format_ident!("{}Events", trait_ident);
+    let client_ident = format_ident!("{}Client", trait_ident);
+    let client_doc = format!("Looks up `{}` actors, see [`{}`].", actor_name.value(), trait_ident);
+
+    let action_sigs = actions.iter().map(|a| &a.sig);
+    let handle_impls = actions.iter().map(|a| {
+        let sig = &a.sig.sig;
+        let name = &a.name;
+        let args = &a.args;
+        quote! {
+            #sig {
+                Ok(::rivetkit_client::handle::ActorHandleStateless::action_typed(
+                    self,
+                    #name,
+                    (#(#args,)*),
+                ).await?)
+            }
+        }
+    });
+    let conn_impls = actions.iter().map(|a| {
+        let sig = &a.sig.sig;
+        let name = &a.name;
+        let args = &a.args;
+        quote! {
+            #sig {
+                Ok(::rivetkit_client::connection::ActorConnectionInner::action_typed(
+                    self,
+                    #name,
+                    (#(#args,)*),
+                ).await?)
+            }
+        }
+    });
+
+    let events_tokens = if events.is_empty() {
+        quote! {}
+    } else {
+        let event_sigs = events.iter().map(|e| {
+            let attrs = &e.attrs;
+            let sig = event_sig(e);
+            quote! { #(#attrs)* #sig; }
+        });
+        let event_impls = events.iter().map(|e| {
+            let sig = event_sig(e);
+            let name = &e.name;
+            quote! {
+                #sig {
+                    ::rivetkit_client::connection::ActorConnectionInner::on_event_typed(
+                        self,
+                        #name,
+                        callback,
+                    ).await
+                }
+            }
+        });
+
+        quote! {
+            #[allow(async_fn_in_trait)]
+            #vis trait #events_ident {
+                #(#event_sigs)*
+            }
+
+            impl #events_ident for ::rivetkit_client::connection::ActorConnection {
+                #(#event_impls)*
+            }
+        }
+    };
+
+    Ok(quote! {
+        #(#attrs)*
+        #[allow(async_fn_in_trait)]
+        #vis trait #trait_ident {
+            #(#action_sigs)*
+        }
+
+        #[doc = #client_doc]
+        #[derive(Debug, Clone, Copy, Default)]
+        #vis struct #client_ident;
+
+        impl #client_ident {
+            /// Name of the actor this client talks to.
+            pub const ACTOR_NAME: &'static str = #actor_name;
+
+            /// Gets a handle to the actor with `key`, see
+            /// [`Client::get`](::rivetkit_client::Client::get).
+            pub fn get(
+                client: &::rivetkit_client::Client,
+                key: ::rivetkit_client::ActorKey,
+                opts: ::rivetkit_client::GetOptions,
+            ) -> ::rivetkit_client::Result<::rivetkit_client::handle::ActorHandle> {
+                client.get(Self::ACTOR_NAME, key, opts)
+            }
+
+            /// Gets a handle to the actor with `key`, creating it if needed, see
+            /// [`Client::get_or_create`](::rivetkit_client::Client::get_or_create).
+            pub fn get_or_create(
+                client: &::rivetkit_client::Client,
+                key: ::rivetkit_client::ActorKey,
+                opts: ::rivetkit_client::GetOrCreateOptions,
+            ) -> ::rivetkit_client::Result<::rivetkit_client::handle::ActorHandle> {
+                client.get_or_create(Self::ACTOR_NAME, key, opts)
+            }
+        }
+
+        // `Ok(..?)` converts the client error into the error type declared
+        // on the trait method
+        #[allow(clippy::needless_question_mark)]
+        impl #trait_ident for ::rivetkit_client::handle::ActorHandle {
+            #(#handle_impls)*
+        }
+
+        #[allow(clippy::needless_question_mark)]
+        impl #trait_ident for ::rivetkit_client::connection::ActorConnection {
+            #(#conn_impls)*
+        }
+
+        #events_tokens
+    })
+}
+
+/// Strips `#[action]`/`#[event]` from the method attributes.
+fn take_method_kind(attrs: &mut Vec<Attribute>) -> syn::Result<MethodKind> {
+    let mut kind = None;
+    let mut rest = Vec::new();
+
+    for attr in attrs.drain(..) {
+        let is_action = attr.path().is_ident("action");
+        let is_event = attr.path().is_ident("event");
+        if !is_action && !is_event {
+            rest.push(attr);
+            continue;
+        }
+
+        if kind.is_some() {
+            return Err(syn::Error::new(attr.span(), "duplicate action/event attribute"));
+        }
+
+        let mut name = None;
+        if !matches!(attr.meta, syn::Meta::Path(_)) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("name") {
+                    name = Some(meta.value()?.parse()?);
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported property"))
+                }
+            })?;
+        }
+
+        kind = Some(if is_action {
+            MethodKind::Action(name)
+        } else {
+            MethodKind::Event(name)
+        });
+    }
+
+    *attrs = rest;
+
+    Ok(kind.unwrap_or(MethodKind::Action(None)))
+}
+
+fn parse_action(method: TraitItemFn, name: Option<LitStr>) -> syn::Result<Action> {
+    let sig = &method.sig;
+
+    if sig.asyncness.is_none() {
+        return Err(syn::Error::new(sig.span(), "actions must be `async fn`, mark events with #[event]"));
+    }
+    if !sig.generics.params.is_empty() {
+        return Err(syn::Error::new(sig.generics.span(), "actions cannot be generic"));
+    }
+    if let ReturnType::Default = sig.output {
+        return Err(syn::Error::new(sig.span(), "actions must return a `Result`"));
+    }
+
+    let mut inputs = sig.inputs.iter();
+    match inputs.next() {
+        Some(FnArg::Receiver(recv)) if recv.reference.is_some() && recv.mutability.is_none() => {}
+        _ => return Err(syn::Error::new(sig.span(), "actions must take `&self`")),
+    }
+
+    let args = inputs
+        .map(|arg| match arg {
+            FnArg::Typed(arg) => arg_ident(&arg.pat),
+            FnArg::Receiver(recv) => Err(syn::Error::new(recv.span(), "unexpected receiver")),
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    Ok(Action {
+        name: name.map(|n| n.value()).unwrap_or_else(|| camel_case(&sig.ident.to_string())),
+        sig: method,
+        args,
+    })
+}
+
+fn parse_event(method: TraitItemFn, name: Option<LitStr>) -> syn::Result<Event> {
+    let sig = &method.sig;
+
+    if sig.asyncness.is_some() {
+        return Err(syn::Error::new(sig.span(), "events cannot be `async`"));
+    }
+    if !sig.generics.params.is_empty() {
+        return Err(syn::Error::new(sig.generics.span(), "events cannot be generic"));
+    }
+    if let ReturnType::Type(_, ty) = &sig.output {
+        return Err(syn::Error::new(ty.span(), "events cannot have a return type"));
+    }
+
+    let arg_types = sig
+        .inputs
+        .iter()
+        .map(|arg| match arg {
+            FnArg::Typed(arg) => Ok((*arg.ty).clone()),
+            FnArg::Receiver(recv) => Err(syn::Error::new(
+                recv.span(),
+                "events describe the event arguments and cannot take `self`",
+            )),
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    Ok(Event {
+        method: format_ident!("on_{}", sig.ident),
+        name: name.map(|n| n.value()).unwrap_or_else(|| camel_case(&sig.ident.to_string())),
+        arg_types,
+        attrs: method.attrs,
+    })
+}
+
+fn event_sig(event: &Event) -> TokenStream2 {
+    let method = &event.method;
+    let arg_types = &event.arg_types;
+    quote! {
+        async fn #method<F>(&self, callback: F) -> ::rivetkit_client::connection::Subscription
+        where
+            F: Fn(::rivetkit_client::Result<(#(#arg_types,)*)>) + Send + Sync + 'static
+    }
+}
+
+fn arg_ident(pat: &Pat) -> syn::Result<Ident> {
+    match pat {
+        Pat::Ident(pat) => Ok(pat.ident.clone()),
+        _ => Err(syn::Error::new(pat.span(), "action arguments must be plain identifiers")),
+    }
+}
+
+fn camel_case(name: &str) -> String {
+    let mut out = String::with_capacity(name.len());
+    let mut upper = false;
+
+    for c in name.trim_start_matches('_').chars() {
+        if c == '_' {
+            upper = true;
+        } else if upper {
+            out.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            out.push(c);
+        }
+    }
+
+    out
+}
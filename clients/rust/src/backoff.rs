@@ -1,24 +1,173 @@
-use std::{cmp, time::Duration};
+use std::{fmt, sync::Arc, time::Duration};
+
+use rand::Rng;
+
+use crate::drivers::DisconnectReason;
+
+/// Randomization applied to reconnect delays, so clients dropped at the same
+/// time do not reconnect in lockstep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Jitter {
+    /// Exact exponential delays.
+    #[default]
+    None,
+    /// Random delay between zero and the exponential delay.
+    Full,
+    /// Random delay between `initial_delay` and `multiplier` times the
+    /// previous delay, capped at `max_delay`.
+    Decorrelated,
+}
+
+/// Retry about to be scheduled, passed to [`ReconnectPolicy::on_retry`].
+#[derive(Debug, Clone)]
+pub struct RetryContext {
+    /// Reconnect attempts since the connection was last healthy, including
+    /// this one.
+    pub attempt: u32,
+    /// Time since the connection was last healthy.
+    pub elapsed: Duration,
+    /// Delay chosen by the policy.
+    pub delay: Duration,
+    /// Why the previous attempt ended.
+    pub reason: DisconnectReason,
+}
+
+/// Returned by [`ReconnectPolicy::on_retry`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryDecision {
+    /// Retry after the delay chosen by the policy.
+    Retry,
+    /// Retry after the given delay instead.
+    RetryAfter(Duration),
+    /// Stop reconnecting, the connection fails with
+    /// [`ConnectionError::RetriesExhausted`](crate::ConnectionError::RetriesExhausted).
+    GiveUp,
+}
+
+/// Hook deciding whether and when to retry, see [`ReconnectPolicy::on_retry`].
+pub type RetryHook = Arc<dyn Fn(&RetryContext) -> RetryDecision + Send + Sync>;
+
+/// Delay between reconnect attempts of an actor connection.
+///
+/// After a connection that stayed open for `healthy_after` drops, the backoff
+/// starts over at `initial_delay`. Connections dropping sooner count as
+/// failed attempts.
+#[derive(Clone)]
+pub struct ReconnectPolicy {
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    /// Factor applied to the delay after every failed attempt.
+    pub multiplier: f64,
+    pub jitter: Jitter,
+    /// Gives up after this many failed attempts in a row.
+    pub max_attempts: Option<u32>,
+    /// Gives up once the connection has been down for this long.
+    pub max_elapsed: Option<Duration>,
+    pub healthy_after: Duration,
+    pub on_retry: Option<RetryHook>,
+}
+
+impl ReconnectPolicy {
+    /// Calls `hook` before every retry to veto it or adjust its delay.
+    pub fn on_retry<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&RetryContext) -> RetryDecision + Send + Sync + 'static,
+    {
+        self.on_retry = Some(Arc::new(hook));
+        self
+    }
+
+    pub(crate) fn decide(&self, ctx: &RetryContext) -> RetryDecision {
+        if self.max_attempts.is_some_and(|max| ctx.attempt > max) {
+            return RetryDecision::GiveUp;
+        }
+        if self.max_elapsed.is_some_and(|max| ctx.elapsed >= max) {
+            return RetryDecision::GiveUp;
+        }
+
+        match &self.on_retry {
+            Some(hook) => hook(ctx),
+            None => RetryDecision::Retry,
+        }
+    }
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+            multiplier: 2.0,
+            jitter: Jitter::default(),
+            max_attempts: None,
+            max_elapsed: None,
+            healthy_after: Duration::ZERO,
+            on_retry: None,
+        }
+    }
+}
+
+impl fmt::Debug for ReconnectPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReconnectPolicy")
+            .field("initial_delay", &self.initial_delay)
+            .field("max_delay", &self.max_delay)
+            .field("multiplier", &self.multiplier)
+            .field("jitter", &self.jitter)
+            .field("max_attempts", &self.max_attempts)
+            .field("max_elapsed", &self.max_elapsed)
+            .field("healthy_after", &self.healthy_after)
+            .field("on_retry", &self.on_retry.is_some())
+            .finish()
+    }
+}
+
+/// `delay * multiplier`, capped at `max`.
+fn scale(delay: Duration, multiplier: f64, max: Duration) -> Duration {
+    Duration::try_from_secs_f64(delay.as_secs_f64() * multiplier.max(1.0))
+        .unwrap_or(max)
+        .min(max)
+}
 
 pub struct Backoff {
-    max_delay: Duration,
+    policy: ReconnectPolicy,
+    /// Exponential delay before jitter.
     delay: Duration,
+    /// Last returned delay, the base for decorrelated jitter.
+    prev: Duration,
 }
 
 impl Backoff {
-    pub fn new(initial: Duration, max_delay: Duration) -> Self {
+    pub fn from_policy(policy: &ReconnectPolicy) -> Self {
         Self {
-            max_delay,
-            delay: initial,
+            policy: policy.clone(),
+            delay: policy.initial_delay,
+            prev: policy.initial_delay,
         }
     }
 
-    pub fn delay(&self) -> Duration {
-        self.delay
+    pub fn reset(&mut self) {
+        self.delay = self.policy.initial_delay;
+        self.prev = self.policy.initial_delay;
     }
 
-    pub async fn tick(&mut self) {
-        tokio::time::sleep(self.delay).await;
-        self.delay = cmp::min(self.delay * 2, self.max_delay);
+    /// Delay before the next attempt.
+    pub fn next_delay(&mut self) -> Duration {
+        let policy = &self.policy;
+        let max_delay = policy.max_delay.max(policy.initial_delay);
+
+        let delay = match policy.jitter {
+            Jitter::None => self.delay,
+            Jitter::Full => rand::thread_rng().gen_range(Duration::ZERO..=self.delay),
+            Jitter::Decorrelated => {
+                let upper = scale(self.prev, policy.multiplier, max_delay);
+                rand::thread_rng().gen_range(policy.initial_delay..=upper.max(policy.initial_delay))
+            }
+        };
+
+        self.delay = scale(self.delay, policy.multiplier, max_delay);
+        self.prev = delay;
+
+        delay
     }
 }
@@ -1,11 +1,16 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
-use anyhow::Result;
 use serde_json::{Value as JsonValue};
 
 use crate::{
-    common::{resolve_actor_id, ActorKey, EncodingKind, TransportKind},
+    backoff::ReconnectPolicy,
+    common::{resolve_actor_id, ActionOptions, ActorKey, DetectedProtocol, EncodingKind, ProtocolHeader, ProtocolVersion, TransportKind, USER_AGENT_VALUE},
+    error::{Error, Result},
+    connection::InFlightPolicy,
+    credentials::CredentialProvider,
+    drivers::HeartbeatPolicy,
     handle::ActorHandle,
+    msg_queue::OfflineQueuePolicy,
     protocol::query::*
 };
 
@@ -31,13 +36,282 @@ pub struct CreateOptions {
     pub params: Option<JsonValue>,
     pub region: Option<String>,
     pub input: Option<JsonValue>,
+    /// Timeout, cancellation & headers for the create request.
+    pub request: ActionOptions,
 }
 
 
+/// Settings shared by every handle & connection created from a [`Client`].
+#[derive(Debug, Clone)]
+pub struct ClientConfig {
+    pub endpoint: String,
+    pub transport_kind: TransportKind,
+    pub encoding_kind: EncodingKind,
+    /// Extra headers sent with every HTTP request, SSE stream & WebSocket
+    /// upgrade.
+    pub headers: Vec<(String, String)>,
+    /// Timeout for stateless HTTP requests (actions, resolve, messages).
+    pub request_timeout: Option<Duration>,
+    /// Timeout for establishing a WebSocket/SSE connection.
+    pub connect_timeout: Option<Duration>,
+    pub reconnect: ReconnectPolicy,
+    pub offline_queue: OfflineQueuePolicy,
+    pub in_flight: InFlightPolicy,
+    pub heartbeat: HeartbeatPolicy,
+    pub credentials: Option<Arc<dyn CredentialProvider>>,
+    /// Capacity of the driver's incoming & outgoing message channels.
+    pub channel_capacity: usize,
+    pub user_agent: String,
+    /// Pooled HTTP client shared by every stateless request & SSE message.
+    pub http_client: reqwest::Client,
+    pub protocol_version: ProtocolVersion,
+    pub(crate) detected_protocol: Arc<DetectedProtocol>,
+}
+
+impl ClientConfig {
+    /// Dialect currently used with the manager. Returns
+    /// [`ProtocolVersion::Auto`] until a connection has detected it.
+    pub fn effective_protocol(&self) -> ProtocolVersion {
+        match self.protocol_version {
+            ProtocolVersion::Auto => self.detected_protocol.get(),
+            version => version,
+        }
+    }
+
+    pub(crate) fn set_detected_protocol(&self, version: ProtocolVersion) {
+        if self.protocol_version == ProtocolVersion::Auto {
+            self.detected_protocol.set(version);
+        }
+    }
+
+    /// Header pairs for `header` in the effective dialect.
+    pub(crate) fn protocol_headers<'a>(
+        &self,
+        header: ProtocolHeader,
+        value: &str,
+    ) -> impl Iterator<Item = (&'a str, String)> {
+        let value = value.to_string();
+        self.effective_protocol()
+            .header_names(header)
+            .iter()
+            .map(move |name| (*name, value.clone()))
+    }
+}
+
+pub const ENV_ENDPOINT: &str = "RIVETKIT_ENDPOINT";
+pub const ENV_TRANSPORT: &str = "RIVETKIT_TRANSPORT";
+pub const ENV_ENCODING: &str = "RIVETKIT_ENCODING";
+
+const DEFAULT_CHANNEL_CAPACITY: usize = 32;
+
+/// Builder for [`Client`].
+///
+/// Defaults are read from `RIVETKIT_ENDPOINT`, `RIVETKIT_TRANSPORT` and
+/// `RIVETKIT_ENCODING` when set, explicit setters take precedence.
+#[derive(Debug, Default)]
+pub struct ClientBuilder {
+    endpoint: Option<String>,
+    transport_kind: Option<TransportKind>,
+    encoding_kind: Option<EncodingKind>,
+    headers: Vec<(String, String)>,
+    request_timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    reconnect: ReconnectPolicy,
+    offline_queue: OfflineQueuePolicy,
+    in_flight: InFlightPolicy,
+    heartbeat: HeartbeatPolicy,
+    credentials: Option<Arc<dyn CredentialProvider>>,
+    channel_capacity: Option<usize>,
+    user_agent_suffix: Option<String>,
+    pool_max_idle_per_host: Option<usize>,
+    pool_idle_timeout: Option<Duration>,
+    tcp_keepalive: Option<Duration>,
+    http_client: Option<reqwest::Client>,
+    protocol_version: ProtocolVersion,
+}
+
+impl ClientBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn endpoint(mut self, endpoint: &str) -> Self {
+        self.endpoint = Some(endpoint.to_string());
+        self
+    }
+
+    pub fn transport(mut self, transport_kind: TransportKind) -> Self {
+        self.transport_kind = Some(transport_kind);
+        self
+    }
+
+    pub fn encoding(mut self, encoding_kind: EncodingKind) -> Self {
+        self.encoding_kind = Some(encoding_kind);
+        self
+    }
+
+    pub fn header(mut self, key: &str, value: &str) -> Self {
+        self.headers.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = Some(timeout);
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    pub fn reconnect_policy(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect = policy;
+        self
+    }
+
+    /// Bounds the messages queued while a connection is down.
+    pub fn offline_queue(mut self, policy: OfflineQueuePolicy) -> Self {
+        self.offline_queue = policy;
+        self
+    }
+
+    /// What happens to sent actions when a connection drops before they
+    /// are answered.
+    pub fn in_flight_policy(mut self, policy: InFlightPolicy) -> Self {
+        self.in_flight = policy;
+        self
+    }
+
+    /// Pings & idle timeouts used to detect dead connections.
+    pub fn heartbeat(mut self, policy: HeartbeatPolicy) -> Self {
+        self.heartbeat = policy;
+        self
+    }
+
+    /// Asks `provider` for connection parameters & headers before every
+    /// request and connection attempt.
+    pub fn credential_provider(mut self, provider: impl CredentialProvider + 'static) -> Self {
+        self.credentials = Some(Arc::new(provider));
+        self
+    }
+
+    pub fn channel_capacity(mut self, capacity: usize) -> Self {
+        self.channel_capacity = Some(capacity);
+        self
+    }
+
+    /// Appended to the default `User-Agent`, e.g. `my-game/1.2.0`.
+    pub fn user_agent_suffix(mut self, suffix: &str) -> Self {
+        self.user_agent_suffix = Some(suffix.to_string());
+        self
+    }
+
+    /// Wire dialect to speak with the manager, defaults to
+    /// [`ProtocolVersion::Current`].
+    pub fn protocol_version(mut self, version: ProtocolVersion) -> Self {
+        self.protocol_version = version;
+        self
+    }
+
+    /// Maximum idle connections kept per host by the HTTP pool.
+    pub fn pool_max_idle_per_host(mut self, max: usize) -> Self {
+        self.pool_max_idle_per_host = Some(max);
+        self
+    }
+
+    /// How long idle pooled HTTP connections are kept alive.
+    pub fn pool_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.pool_idle_timeout = Some(timeout);
+        self
+    }
+
+    pub fn tcp_keepalive(mut self, interval: Duration) -> Self {
+        self.tcp_keepalive = Some(interval);
+        self
+    }
+
+    /// Uses a preconfigured HTTP client, the pool & keep-alive settings of
+    /// this builder are ignored.
+    pub fn http_client(mut self, client: reqwest::Client) -> Self {
+        self.http_client = Some(client);
+        self
+    }
+
+    pub fn build(self) -> Result<Client> {
+        let endpoint = match self.endpoint {
+            Some(endpoint) => endpoint,
+            None => std::env::var(ENV_ENDPOINT).map_err(|_| {
+                Error::Config(format!("missing endpoint, set it explicitly or with {}", ENV_ENDPOINT))
+            })?,
+        };
+
+        let transport_kind = match (self.transport_kind, std::env::var(ENV_TRANSPORT)) {
+            (Some(kind), _) => kind,
+            (None, Ok(kind)) => kind.parse()?,
+            (None, Err(_)) => TransportKind::WebSocket,
+        };
+
+        let encoding_kind = match (self.encoding_kind, std::env::var(ENV_ENCODING)) {
+            (Some(kind), _) => kind,
+            (None, Ok(kind)) => kind.parse()?,
+            (None, Err(_)) => EncodingKind::Cbor,
+        };
+
+        let channel_capacity = self.channel_capacity.unwrap_or(DEFAULT_CHANNEL_CAPACITY);
+        if channel_capacity == 0 {
+            return Err(Error::Config("channel capacity must be greater than 0".to_string()));
+        }
+
+        let user_agent = match self.user_agent_suffix {
+            Some(suffix) => format!("{} {}", USER_AGENT_VALUE, suffix),
+            None => USER_AGENT_VALUE.to_string(),
+        };
+
+        let http_client = match self.http_client {
+            Some(client) => client,
+            None => {
+                let mut builder = reqwest::Client::builder();
+                if let Some(max) = self.pool_max_idle_per_host {
+                    builder = builder.pool_max_idle_per_host(max);
+                }
+                if let Some(timeout) = self.pool_idle_timeout {
+                    builder = builder.pool_idle_timeout(timeout);
+                }
+                if let Some(interval) = self.tcp_keepalive {
+                    builder = builder.tcp_keepalive(interval);
+                }
+                if let Some(timeout) = self.connect_timeout {
+                    builder = builder.connect_timeout(timeout);
+                }
+
+                builder.build().map_err(|e| Error::Config(e.to_string()))?
+            }
+        };
+
+        Ok(Client::from_config(ClientConfig {
+            endpoint,
+            transport_kind,
+            encoding_kind,
+            headers: self.headers,
+            request_timeout: self.request_timeout,
+            connect_timeout: self.connect_timeout,
+            reconnect: self.reconnect,
+            offline_queue: self.offline_queue,
+            in_flight: self.in_flight,
+            heartbeat: self.heartbeat,
+            credentials: self.credentials,
+            channel_capacity,
+            user_agent,
+            http_client,
+            protocol_version: self.protocol_version,
+            detected_protocol: Arc::new(DetectedProtocol::default()),
+        }))
+    }
+}
+
 pub struct Client {
-    manager_endpoint: String,
-    encoding_kind: EncodingKind,
-    transport_kind: TransportKind,
+    config: Arc<ClientConfig>,
     shutdown_tx: Arc<tokio::sync::broadcast::Sender<()>>,
 }
 
@@ -47,26 +321,53 @@ impl Client {
         transport_kind: TransportKind,
         encoding_kind: EncodingKind,
     ) -> Self {
-        Self {
-            manager_endpoint: manager_endpoint.to_string(),
-            encoding_kind,
+        Self::from_config(ClientConfig {
+            endpoint: manager_endpoint.to_string(),
             transport_kind,
+            encoding_kind,
+            headers: Vec::new(),
+            request_timeout: None,
+            connect_timeout: None,
+            reconnect: ReconnectPolicy::default(),
+            offline_queue: OfflineQueuePolicy::default(),
+            in_flight: InFlightPolicy::default(),
+            heartbeat: HeartbeatPolicy::default(),
+            credentials: None,
+            channel_capacity: DEFAULT_CHANNEL_CAPACITY,
+            user_agent: USER_AGENT_VALUE.to_string(),
+            http_client: reqwest::Client::new(),
+            protocol_version: ProtocolVersion::default(),
+            detected_protocol: Arc::new(DetectedProtocol::default()),
+        })
+    }
+
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::new()
+    }
+
+    fn from_config(mut config: ClientConfig) -> Self {
+        config.endpoint = config.endpoint.trim_end_matches('/').to_string();
+
+        Self {
+            config: Arc::new(config),
             shutdown_tx: Arc::new(tokio::sync::broadcast::channel(1).0)
         }
     }
 
+    pub fn config(&self) -> &ClientConfig {
+        &self.config
+    }
+
     fn create_handle(
         &self,
         params: Option<JsonValue>,
         query: ActorQuery
     ) -> ActorHandle {
         let handle = ActorHandle::new(
-            &self.manager_endpoint,
+            self.config.clone(),
             params,
             query,
             self.shutdown_tx.clone(),
-            self.transport_kind,
-            self.encoding_kind
         );
 
         handle
@@ -157,9 +458,9 @@ impl Client {
         };
 
         let actor_id = resolve_actor_id(
-            &self.manager_endpoint,
+            &self.config,
             create_query,
-            self.encoding_kind
+            &opts.request,
         ).await?;
 
         let get_query = ActorQuery::GetForId {
@@ -1,27 +1,149 @@
-use anyhow::Result;
+use std::{
+    future::Future,
+    str::FromStr,
+    sync::atomic::{AtomicU8, Ordering},
+    time::Duration,
+};
+
 use reqwest::{header::USER_AGENT, RequestBuilder};
 use serde::{de::DeserializeOwned, Serialize};
 use serde_json::{json, Value as JsonValue};
+use tokio_util::sync::CancellationToken;
 use tracing::debug;
 
-use crate::protocol::query::ActorQuery;
+use crate::{
+    client::ClientConfig,
+    credentials::{credentials, RequestKind},
+    error::{Error, Result},
+    protocol::{http::ResponseError, query::ActorQuery},
+    telemetry,
+};
 
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 pub const USER_AGENT_VALUE: &str = concat!("ActorClient-Rust/", env!("CARGO_PKG_VERSION"));
 
-pub const HEADER_ACTOR_QUERY: &str = "X-AC-Query";
-pub const HEADER_ENCODING: &str = "X-AC-Encoding";
-pub const HEADER_CONN_PARAMS: &str = "X-AC-Conn-Params";
-pub const HEADER_ACTOR_ID: &str = "X-AC-Actor";
-pub const HEADER_CONN_ID: &str = "X-AC-Conn";
-pub const HEADER_CONN_TOKEN: &str = "X-AC-Conn-Token";
+pub const HEADER_ACTOR_QUERY: &str = "X-RivetKit-Query";
+pub const HEADER_ENCODING: &str = "X-RivetKit-Encoding";
+pub const HEADER_CONN_PARAMS: &str = "X-RivetKit-Conn-Params";
+pub const HEADER_ACTOR_ID: &str = "X-RivetKit-Actor";
+pub const HEADER_CONN_ID: &str = "X-RivetKit-Conn";
+pub const HEADER_CONN_TOKEN: &str = "X-RivetKit-Conn-Token";
+
+pub const LEGACY_HEADER_ACTOR_QUERY: &str = "X-AC-Query";
+pub const LEGACY_HEADER_ENCODING: &str = "X-AC-Encoding";
+pub const LEGACY_HEADER_CONN_PARAMS: &str = "X-AC-Conn-Params";
+pub const LEGACY_HEADER_ACTOR_ID: &str = "X-AC-Actor";
+pub const LEGACY_HEADER_CONN_ID: &str = "X-AC-Conn";
+pub const LEGACY_HEADER_CONN_TOKEN: &str = "X-AC-Conn-Token";
+
+// WebSocket subprotocol prefixes, used instead of headers since they can't be
+// set by browsers
+pub const WS_PROTOCOL_QUERY: &str = "query.";
+pub const WS_PROTOCOL_ENCODING: &str = "encoding.";
+pub const WS_PROTOCOL_CONN_PARAMS: &str = "conn_params.";
+pub const WS_PROTOCOL_STANDARD: &str = "rivetkit";
+
+/// Wire dialect spoken with the manager.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProtocolVersion {
+    /// Detect the dialect from the first WebSocket connection. HTTP requests
+    /// send both header sets until the dialect is known, so clients that
+    /// never open a WebSocket keep sending both.
+    Auto,
+    /// `X-RivetKit-*` headers, WebSocket parameters sent as subprotocols.
+    #[default]
+    Current,
+    /// `X-AC-*` headers, WebSocket parameters sent in the query string and
+    /// the init message.
+    Legacy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ProtocolHeader {
+    ActorQuery,
+    Encoding,
+    ConnParams,
+    ActorId,
+    ConnId,
+    ConnToken,
+}
+
+impl ProtocolHeader {
+    fn names(self) -> &'static [&'static str; 2] {
+        match self {
+            ProtocolHeader::ActorQuery => &[HEADER_ACTOR_QUERY, LEGACY_HEADER_ACTOR_QUERY],
+            ProtocolHeader::Encoding => &[HEADER_ENCODING, LEGACY_HEADER_ENCODING],
+            ProtocolHeader::ConnParams => &[HEADER_CONN_PARAMS, LEGACY_HEADER_CONN_PARAMS],
+            ProtocolHeader::ActorId => &[HEADER_ACTOR_ID, LEGACY_HEADER_ACTOR_ID],
+            ProtocolHeader::ConnId => &[HEADER_CONN_ID, LEGACY_HEADER_CONN_ID],
+            ProtocolHeader::ConnToken => &[HEADER_CONN_TOKEN, LEGACY_HEADER_CONN_TOKEN],
+        }
+    }
+}
+
+impl ProtocolVersion {
+    /// Header names to send for `header`, both dialects while undetected.
+    pub(crate) fn header_names(self, header: ProtocolHeader) -> &'static [&'static str] {
+        let names = header.names();
+        match self {
+            ProtocolVersion::Auto => names,
+            ProtocolVersion::Current => &names[..1],
+            ProtocolVersion::Legacy => &names[1..],
+        }
+    }
+}
+
+/// Dialect detected for [`ProtocolVersion::Auto`], shared by every handle and
+/// connection of a client.
+#[derive(Debug, Default)]
+pub(crate) struct DetectedProtocol(AtomicU8);
+
+impl DetectedProtocol {
+    pub fn get(&self) -> ProtocolVersion {
+        match self.0.load(Ordering::Relaxed) {
+            1 => ProtocolVersion::Current,
+            2 => ProtocolVersion::Legacy,
+            _ => ProtocolVersion::Auto,
+        }
+    }
 
-#[derive(Debug, Clone, Copy)]
+    pub fn set(&self, version: ProtocolVersion) {
+        let value = match version {
+            ProtocolVersion::Auto => 0,
+            ProtocolVersion::Current => 1,
+            ProtocolVersion::Legacy => 2,
+        };
+        self.0.store(value, Ordering::Relaxed);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TransportKind {
     WebSocket,
     Sse,
 }
 
+impl TransportKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransportKind::WebSocket => "websocket",
+            TransportKind::Sse => "sse",
+        }
+    }
+}
+
+impl FromStr for TransportKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "websocket" | "ws" => Ok(TransportKind::WebSocket),
+            "sse" => Ok(TransportKind::Sse),
+            _ => Err(Error::Config(format!("unknown transport: {}", s))),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum EncodingKind {
     Json,
@@ -29,7 +151,7 @@ pub enum EncodingKind {
 }
 
 impl EncodingKind {
-    pub fn as_str(&self) -> &str {
+    pub fn as_str(&self) -> &'static str {
         match self {
             EncodingKind::Json => "json",
             EncodingKind::Cbor => "cbor",
@@ -43,11 +165,82 @@ impl ToString for EncodingKind {
     }
 }
 
+impl FromStr for EncodingKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(EncodingKind::Json),
+            "cbor" => Ok(EncodingKind::Cbor),
+            _ => Err(Error::Config(format!("unknown encoding: {}", s))),
+        }
+    }
+}
+
 
 
 // Max size of each entry is 128 bytes
 pub type ActorKey = Vec<String>;
 
+/// Per-call options for actions, `resolve` and `create`.
+#[derive(Debug, Clone, Default)]
+pub struct ActionOptions {
+    /// Fails the call with [`Error::Timeout`] if it has not completed in time.
+    pub timeout: Option<Duration>,
+    /// Fails the call with [`Error::Cancelled`] once cancelled.
+    pub cancel: CancellationToken,
+    /// Extra headers for this call. Only applies to HTTP requests, actions
+    /// sent over a connection ignore them.
+    pub headers: Vec<(String, String)>,
+    /// The action is safe to run more than once, see
+    /// [`InFlightPolicy::ResendIdempotent`](crate::InFlightPolicy::ResendIdempotent).
+    /// Only applies to actions sent over a connection.
+    pub idempotent: bool,
+}
+
+impl ActionOptions {
+    pub(crate) fn header_pairs(&self) -> impl Iterator<Item = (&str, String)> {
+        self.headers.iter().map(|(k, v)| (k.as_str(), v.clone()))
+    }
+
+    /// Runs `fut`, bounded by the timeout & cancellation token.
+    pub(crate) async fn run<T>(&self, fut: impl Future<Output = Result<T>>) -> Result<T> {
+        let fut = async {
+            match self.timeout {
+                Some(timeout) => tokio::time::timeout(timeout, fut)
+                    .await
+                    .map_err(|_| Error::Timeout)?,
+                None => fut.await,
+            }
+        };
+
+        tokio::select! {
+            biased;
+            _ = self.cancel.cancelled() => Err(Error::Cancelled),
+            res = fut => res,
+        }
+    }
+}
+
+/// Converts typed action arguments to the positional argument array.
+///
+/// `args` must serialize to a sequence (e.g. a tuple or a `Vec`), `()` is
+/// treated as no arguments.
+pub(crate) fn serialize_args<A: Serialize>(args: A) -> Result<Vec<JsonValue>> {
+    match serde_json::to_value(args).map_err(Error::serialization)? {
+        JsonValue::Array(args) => Ok(args),
+        JsonValue::Null => Ok(Vec::new()),
+        other => Err(Error::serialization(format!(
+            "action arguments must serialize to a sequence, got {}",
+            other
+        ))),
+    }
+}
+
+pub(crate) fn deserialize_value<R: DeserializeOwned>(value: JsonValue) -> Result<R> {
+    serde_json::from_value(value).map_err(Error::deserialization)
+}
+
 pub struct HttpRequestOptions<'a, T: Serialize> {
     pub method: &'a str,
     pub url: &'a str,
@@ -68,15 +261,21 @@ impl<'a, T: Serialize> Default for HttpRequestOptions<'a, T> {
     }
 }
 
-fn build_http_request<RQ>(opts: &HttpRequestOptions<RQ>) -> Result<RequestBuilder>
+fn build_http_request<RQ>(config: &ClientConfig, opts: &HttpRequestOptions<RQ>) -> Result<RequestBuilder>
 where
     RQ: Serialize
 {
-    let client = reqwest::Client::new();
-    let mut req = client.request(
-        reqwest::Method::from_bytes(opts.method.as_bytes()).unwrap(),
-        opts.url,
-    );
+    let method = reqwest::Method::from_bytes(opts.method.as_bytes())
+        .map_err(Error::serialization)?;
+    let mut req = config.http_client.request(method, opts.url);
+
+    if let Some(timeout) = config.request_timeout {
+        req = req.timeout(timeout);
+    }
+
+    for (key, value) in &config.headers {
+        req = req.header(key, value);
+    }
 
     for (key, value) in &opts.headers {
         req = req.header(*key, value);
@@ -84,116 +283,130 @@ where
     
     if opts.method == "POST" || opts.method == "PUT" {
         let Some(body) = &opts.body else {
-            return Err(anyhow::anyhow!("Body is required for POST/PUT requests"));
+            return Err(Error::serialization("Body is required for POST/PUT requests"));
         };
 
         match opts.encoding_kind {
             EncodingKind::Json => {
                 req = req.header("Content-Type", "application/json");
-                let body = serde_json::to_string(&body)?;
+                let body = serde_json::to_string(&body).map_err(Error::serialization)?;
+                telemetry::message_sent(opts.encoding_kind, body.len());
                 req = req.body(body);
             }
             EncodingKind::Cbor => {
                 req = req.header("Content-Type", "application/octet-stream");
-                let body =serde_cbor::to_vec(&body)?;
+                let body = serde_cbor::to_vec(&body).map_err(Error::serialization)?;
+                telemetry::message_sent(opts.encoding_kind, body.len());
                 req = req.body(body);
             }
         }
     };
 
-    req = req.header(USER_AGENT, USER_AGENT_VALUE);
+    req = req.header(USER_AGENT, &config.user_agent);
 
     Ok(req)
 }
 
-async fn send_http_request_raw(req: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+async fn send_http_request_raw(
+    req: reqwest::RequestBuilder,
+    encoding_kind: EncodingKind
+) -> Result<reqwest::Response> {
     let res = req.send().await?;
 
     if !res.status().is_success() {
-        // TODO: Decode
-        /*
-        let data: Option<RpcResponseError> = match opts.encoding_kind {
-            EncodingKind::Json => {
-                let data = res.text().await?;
-                
-                serde_json::from_str::<RpcResponseError>(&data).ok()
-            }
-            EncodingKind::Cbor => {
-                let data = res.bytes().await?;
-                
-                serde_cbor::from_slice(&data).ok()
-            }
-        };
+        let status = res.status().as_u16();
+        let body = res.bytes().await?;
+
+        return Err(decode_response_error(status, &body, encoding_kind));
+    }
+
+    Ok(res)
+}
+
+pub(crate) fn decode_response_error(status: u16, body: &[u8], encoding_kind: EncodingKind) -> Error {
+    let data: Option<ResponseError> = match encoding_kind {
+        EncodingKind::Json => serde_json::from_slice(body).ok(),
+        EncodingKind::Cbor => serde_cbor::from_slice(body).ok(),
+    };
 
-        match data {
-            Some(data) => {
-                return Err(anyhow::anyhow!(
-                    "HTTP request failed with status: {}, error: {}",
-                    res.status(),
-                    data.m
-                ));
-            },
-            None => {
+    match data {
+        Some(data) => data.into(),
+        None => {
+            // Not a structured error (e.g. a proxy in front of the manager),
+            // so fall back to the raw response
+            debug!("failed to decode error response, status: {}", status);
 
+            Error::HttpStatus {
+                status,
+                body: String::from_utf8_lossy(body).into_owned(),
             }
         }
-        */
-        return Err(anyhow::anyhow!(
-            "HTTP request failed with status: {}",
-            res.status()
-        ));
     }
-
-    Ok(res)
 }
 
-pub async fn send_http_request<'a, RQ, RS>(opts: HttpRequestOptions<'a, RQ>) -> Result<RS>
+pub async fn send_http_request<'a, RQ, RS>(
+    config: &ClientConfig,
+    opts: HttpRequestOptions<'a, RQ>
+) -> Result<RS>
 where
     RQ: Serialize,
     RS: DeserializeOwned,
 {
-    let req = build_http_request(&opts)?;
-    let res = send_http_request_raw(req).await?;
+    let req = build_http_request(config, &opts)?;
+    let res = send_http_request_raw(req, opts.encoding_kind).await?;
 
-    let res: RS = match opts.encoding_kind {
-        EncodingKind::Json => {
-            let data = res.text().await?;
-            serde_json::from_str(&data)?
-        }
-        EncodingKind::Cbor => {
-            let bytes = res.bytes().await?;
-            serde_cbor::from_slice(&bytes)?
-        }
+    let bytes = res.bytes().await?;
+    telemetry::message_received(opts.encoding_kind, bytes.len());
+
+    let res = match opts.encoding_kind {
+        EncodingKind::Json => serde_json::from_slice(&bytes).map_err(Error::malformed),
+        EncodingKind::Cbor => serde_cbor::from_slice(&bytes).map_err(Error::malformed),
     };
+    if res.is_err() {
+        telemetry::deserialization_failed(opts.encoding_kind);
+    }
 
-    Ok(res)
+    res
 }
 
 
 pub async fn resolve_actor_id(
-    manager_endpoint: &str,
+    config: &ClientConfig,
     query: ActorQuery,
-    encoding_kind: EncodingKind
+    opts: &ActionOptions,
 ) -> Result<String> {
     #[derive(serde::Serialize, serde::Deserialize)]
     struct ResolveResponse {
         i: String,
     }
 
-    let query = serde_json::to_string(&query)?;
+    let credentials = credentials(config, RequestKind::Resolve, &query, None).await?;
+    let query = serde_json::to_string(&query).map_err(Error::serialization)?;
+
+    let mut headers = Vec::new();
+    headers.extend(config.protocol_headers(ProtocolHeader::Encoding, config.encoding_kind.as_str()));
+    headers.extend(config.protocol_headers(ProtocolHeader::ActorQuery, &query));
+    if let Some(params) = &credentials.params {
+        let params = serde_json::to_string(params).map_err(Error::serialization)?;
+        headers.extend(config.protocol_headers(ProtocolHeader::ConnParams, &params));
+    }
+    headers.extend(credentials.header_pairs());
+    headers.extend(opts.header_pairs());
 
-    let res = send_http_request::<JsonValue, ResolveResponse>(
+    let res = opts.run(send_http_request::<JsonValue, ResolveResponse>(
+        config,
         HttpRequestOptions {
             method: "POST",
-            url: &format!("{}/actors/resolve", manager_endpoint),
-            headers: vec![
-                (HEADER_ENCODING, encoding_kind.to_string()),
-                (HEADER_ACTOR_QUERY, query),
-            ],
+            url: &format!("{}/actors/resolve", config.endpoint),
+            headers,
             body: Some(json!({})),
-            encoding_kind,
+            encoding_kind: config.encoding_kind,
         }
-    ).await?;
+    )).await.map_err(|err| match err {
+        // Structured errors, e.g. `not_found`, stay actor errors
+        Error::HttpStatus { .. } | Error::MalformedResponse(_) => Error::Manager(err.to_string()),
+        err => err,
+    })?;
 
     Ok(res.i)
 }
\ No newline at end of file
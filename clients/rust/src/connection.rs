@@ -1,33 +1,306 @@
-use anyhow::Result;
-use futures_util::FutureExt;
+use futures_util::{future::BoxFuture, FutureExt};
+use serde::{de::DeserializeOwned, Serialize};
 use serde_json::Value;
 use std::fmt::Debug;
+use std::future::Future;
 use std::ops::Deref;
-use std::sync::atomic::{AtomicI64, Ordering};
-use std::time::Duration;
-use std::{collections::HashMap, sync::Arc};
-use tokio::sync::{broadcast, oneshot, watch, Mutex};
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
+use tokio::time::Instant;
+use std::{collections::HashMap, sync::{Arc, Weak}};
+use tokio::sync::{broadcast, mpsc, oneshot, watch, Mutex, Notify};
 
 use crate::{
-    backoff::Backoff,
+    backoff::{Backoff, RetryContext, RetryDecision},
+    client::ClientConfig,
+    common::{deserialize_value, serialize_args, ActionOptions, EncodingKind, TransportKind},
+    credentials::{credentials, RequestKind},
+    error::{ConnectionError, Error, Result},
+    events::{Event, EventSender, EventStream, EventStreamOptions},
+    msg_queue::{DroppedMsg, MsgQueue},
+    telemetry::{self, InFlightGauge},
     protocol::{query::ActorQuery, *},
     drivers::*,
-    EncodingKind,
-    TransportKind
 };
 use tracing::debug;
 
 
-type RpcResponse = Result<to_client::ActionResponse, to_client::Error>;
+type RpcResponse = Result<to_client::ActionResponse, Error>;
 type EventCallback = dyn Fn(&Vec<Value>) + Send + Sync;
+type AsyncEventCallback = dyn Fn(Event) -> BoxFuture<'static, ()> + Send + Sync;
+type StatusCallback = dyn Fn(&ConnectionStatus) + Send + Sync;
+type ErrorCallback = dyn Fn(&Error) + Send + Sync;
+
+/// Lifecycle of an [`ActorConnection`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionStatus {
+    /// Initial connection attempt in progress.
+    Connecting,
+    Connected {
+        actor_id: String,
+        connection_id: String,
+    },
+    /// Waiting `next_delay` before reconnect attempt number `attempt`.
+    Reconnecting {
+        attempt: u32,
+        next_delay: Duration,
+    },
+    Disconnected {
+        reason: DisconnectReason,
+    },
+    /// Gave up after an error that retrying cannot fix, e.g. an
+    /// `unauthorized` handshake. Terminal, the connection will not reconnect.
+    Failed {
+        error: ConnectionError,
+    },
+}
+
+/// Server connection an [`ActorConnection`] is attached to, returned by
+/// [`ActorConnectionInner::ready`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectionInfo {
+    pub actor_id: String,
+    pub connection_id: String,
+    pub transport: TransportKind,
+    pub encoding: EncodingKind,
+    /// When the handshake completed.
+    pub connected_since: SystemTime,
+}
+
+/// What happens to actions that were sent but not answered when the
+/// connection drops.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InFlightPolicy {
+    /// Fails them with [`Error::ConnectionLost`].
+    ///
+    /// Actions run at most once, but a failed action may or may not have run
+    /// on the actor.
+    #[default]
+    Fail,
+    /// Sends actions marked [`idempotent`](ActionOptions::idempotent) again
+    /// once reconnected, others fail like with [`InFlightPolicy::Fail`].
+    ///
+    /// Idempotent actions run at least once and may run more than once. They
+    /// are kept until the connection reopens regardless of the
+    /// [`OfflineQueuePolicy`](crate::OfflineQueuePolicy).
+    ResendIdempotent,
+}
+
+/// Action waiting for its response.
+struct InFlightAction {
+    tx: oneshot::Sender<RpcResponse>,
+    request: Arc<to_server::ToServer>,
+    idempotent: bool,
+    /// Set once handed to a driver, cleared when that connection drops.
+    sent: bool,
+    _gauge: InFlightGauge,
+}
+
+/// Server error codes that fail the connection instead of reconnecting.
+const FATAL_ERROR_CODES: &[&str] = &[
+    "unauthorized",
+    "forbidden",
+    "actor_not_found",
+    "invalid_params",
+    "invalid_query_json",
+    "invalid_request",
+    "invalid_encoding",
+];
+
+/// Whether an upgrade rejected with `status` is worth retrying.
+fn is_retryable_status(status: u16) -> bool {
+    // Timeouts, rate limiting & server errors are transient
+    !(400..500).contains(&status) || matches!(status, 408 | 425 | 429)
+}
+
+/// Classifies an error from opening the driver, `Some` if it is fatal.
+fn fatal_connect_error(err: &Error) -> Option<ConnectionError> {
+    match err {
+        Error::Config(msg) => Some(ConnectionError::Config(msg.clone())),
+        Error::Serialization(e) => Some(ConnectionError::Config(e.to_string())),
+        Error::Transport(e) => {
+            if let Some(e) = e.downcast_ref::<tungstenite::Error>() {
+                return match e {
+                    tungstenite::Error::Url(e) => Some(ConnectionError::Config(e.to_string())),
+                    tungstenite::Error::HttpFormat(e) => Some(ConnectionError::Config(e.to_string())),
+                    tungstenite::Error::Http(res) if !is_retryable_status(res.status().as_u16()) => {
+                        Some(ConnectionError::HttpStatus {
+                            status: res.status().as_u16(),
+                            body: res.body()
+                                .as_deref()
+                                .map(|b| String::from_utf8_lossy(b).into_owned())
+                                .unwrap_or_default(),
+                        })
+                    }
+                    _ => None,
+                };
+            }
+
+            if let Some(eventsource_client::Error::InvalidParameter(e)) = e.downcast_ref() {
+                return Some(ConnectionError::Config(e.to_string()));
+            }
+
+            None
+        }
+        _ => None,
+    }
+}
+
+struct EventListener {
+    id: u64,
+    /// Cleared as soon as the [`Subscription`] is dropped, before the
+    /// listener is removed.
+    active: Arc<AtomicBool>,
+    kind: ListenerKind,
+}
+
+#[derive(Clone)]
+enum ListenerKind {
+    Callback(Arc<EventCallback>),
+    AsyncCallback(Arc<AsyncEventCallback>),
+    Stream(Arc<EventSender>),
+}
+
+/// Event handed to the dispatcher task along with the listeners registered
+/// when it was received.
+struct Dispatch {
+    event: Event,
+    /// `None` for [`ActorConnectionInner::all_events`] streams, which cannot
+    /// be deactivated.
+    listeners: Vec<(Option<Arc<AtomicBool>>, ListenerKind)>,
+}
+
+/// Text of a caught panic.
+fn panic_message(panic: &(dyn std::any::Any + Send)) -> String {
+    if let Some(msg) = panic.downcast_ref::<&str>() {
+        msg.to_string()
+    } else if let Some(msg) = panic.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+/// Delivers events in the order they were received, one listener at a
+/// time, so slow or panicking listeners never hold up the connection task.
+async fn dispatch_events(conn: Weak<ActorConnectionInner>, mut rx: mpsc::UnboundedReceiver<Dispatch>) {
+    while let Some(Dispatch { event, listeners }) = rx.recv().await {
+        for (active, kind) in listeners {
+            // Skip listeners removed after the event was queued
+            if active.is_some_and(|active| !active.load(Ordering::SeqCst)) {
+                continue;
+            }
+
+            let res = match kind {
+                ListenerKind::Callback(callback) => {
+                    std::panic::catch_unwind(AssertUnwindSafe(|| callback(&event.args)))
+                }
+                ListenerKind::AsyncCallback(callback) => {
+                    AssertUnwindSafe(async { callback(event.clone()).await })
+                        .catch_unwind()
+                        .await
+                }
+                ListenerKind::Stream(tx) => {
+                    tx.send(event.clone()).await;
+                    Ok(())
+                }
+            };
+
+            if let Err(panic) = res {
+                let err = Error::EventCallbackPanicked {
+                    event: event.name.clone(),
+                    message: panic_message(panic.as_ref()),
+                };
+                debug!("{}", err);
+
+                let Some(conn) = conn.upgrade() else {
+                    return;
+                };
+                conn.report_error(&err).await;
+            }
+        }
+    }
+}
+
+/// Event listener registered with [`ActorConnectionInner::on_event`].
+///
+/// The listener is removed when the subscription is dropped or
+/// [`unsubscribed`](Subscription::unsubscribe). Once the last listener for an
+/// event is gone the server stops sending it.
+#[must_use = "the listener is removed when the subscription is dropped"]
+pub struct Subscription {
+    conn: Option<Weak<ActorConnectionInner>>,
+    event_name: String,
+    id: u64,
+    active: Arc<AtomicBool>,
+}
+
+impl Subscription {
+    pub fn event_name(&self) -> &str {
+        &self.event_name
+    }
+
+    /// Removes the listener, waiting until the server has been told to stop
+    /// sending the event if this was its last listener.
+    pub async fn unsubscribe(mut self) {
+        self.active.store(false, Ordering::SeqCst);
+
+        if let Some(conn) = self.conn.take().and_then(|c| c.upgrade()) {
+            conn.remove_event_listener(&self.event_name, self.id).await;
+        }
+    }
+
+    /// Keeps the listener for the lifetime of the connection.
+    pub fn detach(mut self) {
+        self.conn = None;
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        let Some(conn) = self.conn.take() else {
+            return;
+        };
+
+        self.active.store(false, Ordering::SeqCst);
+
+        let Some(conn) = conn.upgrade() else {
+            return;
+        };
+        // Removal needs the async locks, without a runtime the connection is
+        // gone anyway
+        let Ok(rt) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+
+        let event_name = std::mem::take(&mut self.event_name);
+        let id = self.id;
+        rt.spawn(async move {
+            conn.remove_event_listener(&event_name, id).await;
+        });
+    }
+}
+
+impl Debug for Subscription {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Subscription")
+            .field("event_name", &self.event_name)
+            .field("id", &self.id)
+            .finish()
+    }
+}
 
 struct SendMsgOpts {
     ephemeral: bool,
+    /// Idempotent action lost with a previous connection, kept out of the
+    /// offline queue if it cannot be sent.
+    resend: bool,
 }
 
 impl Default for SendMsgOpts {
     fn default() -> Self {
-        Self { ephemeral: false }
+        Self { ephemeral: false, resend: false }
     }
 }
 
@@ -39,25 +312,58 @@ type WatchPair = (watch::Sender<bool>, watch::Receiver<bool>);
 
 pub type ActorConnection = Arc<ActorConnectionInner>;
 
+/// Outcome of the current connection handshake, awaited by
+/// [`ActorConnectionInner::ready`].
+#[derive(Debug, Clone)]
+enum ReadyState {
+    Pending,
+    Ready(ConnectionInfo),
+    Failed(ConnectionError),
+}
+
 struct ConnectionAttempt {
-    did_open: bool,
-    _task_end_reason: DriverStopReason,
+    /// How long the connection stayed open, `None` if it never did.
+    open_for: Option<Duration>,
+    end_reason: DisconnectReason,
+    /// Set if the attempt failed in a way that retrying cannot fix.
+    fatal: Option<ConnectionError>,
+    /// Generation of the parameters the attempt connected with.
+    params_generation: u64,
 }
 
 pub struct ActorConnectionInner {
-    endpoint: String,
-    transport_kind: TransportKind,
-    encoding_kind: EncodingKind,
+    config: Arc<ClientConfig>,
     query: ActorQuery,
-    parameters: Option<Value>,
+    parameters: Mutex<Option<Value>>,
+    /// Bumped by [`ActorConnectionInner::set_params`], so connections made
+    /// with older parameters are replaced without counting as a failed
+    /// attempt.
+    params_generation: AtomicU64,
+    /// Cuts a pending reconnect delay short.
+    reconnect_now: Notify,
 
     driver: Mutex<Option<DriverHandle>>,
-    msg_queue: Mutex<Vec<Arc<to_server::ToServer>>>,
+    msg_queue: Mutex<MsgQueue>,
+    /// Actions re-sent by [`InFlightPolicy::ResendIdempotent`]. They were
+    /// accepted once already, so the
+    /// [`OfflineQueuePolicy`](crate::OfflineQueuePolicy) does not apply.
+    resend: Mutex<Vec<Arc<to_server::ToServer>>>,
 
     rpc_counter: AtomicI64,
-    in_flight_rpcs: Mutex<HashMap<i64, oneshot::Sender<RpcResponse>>>,
+    in_flight_rpcs: Mutex<HashMap<i64, InFlightAction>>,
 
-    event_subscriptions: Mutex<HashMap<String, Vec<Box<EventCallback>>>>,
+    listener_counter: AtomicU64,
+    event_subscriptions: Mutex<HashMap<String, Vec<EventListener>>>,
+    /// Streams returned by [`ActorConnectionInner::all_events`].
+    all_event_streams: Mutex<Vec<Arc<EventSender>>>,
+    dispatch_tx: mpsc::UnboundedSender<Dispatch>,
+    /// Taken by [`start_connection`] to spawn the dispatcher task.
+    dispatch_rx: std::sync::Mutex<Option<mpsc::UnboundedReceiver<Dispatch>>>,
+
+    status: watch::Sender<ConnectionStatus>,
+    status_callbacks: Mutex<Vec<Box<StatusCallback>>>,
+    error_callbacks: Mutex<Vec<Box<ErrorCallback>>>,
+    ready: watch::Sender<ReadyState>,
 
     dc_watch: WatchPair,
     disconnection_rx: Mutex<Option<oneshot::Receiver<()>>>,
@@ -65,23 +371,33 @@ pub struct ActorConnectionInner {
 
 impl ActorConnectionInner {
     pub(crate) fn new(
-        endpoint: String,
+        config: Arc<ClientConfig>,
         query: ActorQuery,
-        transport_kind: TransportKind,
-        encoding_kind: EncodingKind,
         parameters: Option<Value>,
     ) -> ActorConnection {
+        let (dispatch_tx, dispatch_rx) = mpsc::unbounded_channel();
+        let msg_queue = MsgQueue::new(config.offline_queue.clone());
+
         Arc::new(Self {
-            endpoint: endpoint.clone(),
-            transport_kind,
-            encoding_kind,
+            config,
             query,
-            parameters,
+            parameters: Mutex::new(parameters),
+            params_generation: AtomicU64::new(0),
+            reconnect_now: Notify::new(),
             driver: Mutex::new(None),
-            msg_queue: Mutex::new(Vec::new()),
+            msg_queue: Mutex::new(msg_queue),
+            resend: Mutex::new(Vec::new()),
             rpc_counter: AtomicI64::new(0),
             in_flight_rpcs: Mutex::new(HashMap::new()),
+            listener_counter: AtomicU64::new(0),
             event_subscriptions: Mutex::new(HashMap::new()),
+            all_event_streams: Mutex::new(Vec::new()),
+            dispatch_tx,
+            dispatch_rx: std::sync::Mutex::new(Some(dispatch_rx)),
+            status: watch::channel(ConnectionStatus::Connecting).0,
+            status_callbacks: Mutex::new(Vec::new()),
+            error_callbacks: Mutex::new(Vec::new()),
+            ready: watch::channel(ReadyState::Pending).0,
             dc_watch: watch::channel(false),
             disconnection_rx: Mutex::new(None),
         })
@@ -92,25 +408,58 @@ impl ActorConnectionInner {
     }
 
     async fn try_connect(self: &Arc<Self>) -> ConnectionAttempt {
-        let Ok((driver, mut recver, task)) = connect_driver(
-            self.transport_kind,
+        let (parameters, params_generation) = {
+            let parameters = self.parameters.lock().await;
+            (parameters.clone(), self.params_generation.load(Ordering::SeqCst))
+        };
+        let credentials = match credentials(
+            &self.config,
+            RequestKind::Connect,
+            &self.query,
+            parameters.as_ref(),
+        ).await {
+            Ok(credentials) => credentials,
+            Err(err) => {
+                debug!("Failed to get credentials: {:?}", err);
+                return ConnectionAttempt {
+                    open_for: None,
+                    end_reason: DriverStopReason::TaskError.into(),
+                    fatal: Some(ConnectionError::Auth(err.to_string())),
+                    params_generation,
+                };
+            }
+        };
+
+        let res = connect_driver(
+            self.config.transport_kind,
             DriverConnectArgs {
-                endpoint: self.endpoint.clone(),
+                config: self.config.clone(),
                 query: self.query.clone(),
-                encoding_kind: self.encoding_kind,
-                parameters: self.parameters.clone(),
+                parameters: credentials.params,
+                headers: credentials.headers,
+            }
+        ).await;
+        let (driver, mut recver, task) = match res {
+            Ok(conn) => conn,
+            Err(err) => {
+                // Either from immediate disconnect (local device connection refused)
+                // or from error like invalid URL
+                debug!("Failed to connect: {:?}", err);
+                return ConnectionAttempt {
+                    open_for: None,
+                    end_reason: DriverStopReason::TaskError.into(),
+                    fatal: fatal_connect_error(&err),
+                    params_generation,
+                };
             }
-        ).await else {
-            // Either from immediate disconnect (local device connection refused)
-            // or from error like invalid URL
-            return ConnectionAttempt {
-                did_open: false,
-                _task_end_reason: DriverStopReason::TaskError,
-            };
         };
 
         {
             let mut my_driver = self.driver.lock().await;
+            // Parameters changed while connecting
+            if self.params_generation.load(Ordering::SeqCst) != params_generation {
+                driver.disconnect();
+            }
             *my_driver = Some(driver);
         }
 
@@ -119,14 +468,16 @@ impl ActorConnectionInner {
             Err(task_err) => {
                 if task_err.is_cancelled() {
                     debug!("Connection task was cancelled");
-                    DriverStopReason::UserAborted
+                    DriverStopReason::UserAborted.into()
                 } else {
-                    DriverStopReason::TaskError
+                    DriverStopReason::TaskError.into()
                 }
             }
         });
 
         let mut did_connection_open = false;
+        let mut opened_at = None;
+        let mut fatal = None;
 
         // spawn listener for rpcs
         let task_end_reason = loop {
@@ -136,18 +487,40 @@ impl ActorConnectionInner {
 
                     break reason;
                 },
-                msg = recver.recv() => {
+                event = recver.recv() => {
                     // If the sender is dropped, break the loop
-                    let Some(msg) = msg else {
+                    let Some(event) = event else {
                         // break DriverStopReason::ServerDisconnect;
                         continue;
                     };
+                    let msg = match event {
+                        DriverEvent::Message(msg) => msg,
+                        DriverEvent::SendFailed(msg, error) => {
+                            self.on_send_failed(msg, error).await;
+                            continue;
+                        }
+                    };
 
-                    if let to_client::ToClientBody::Init { i: _ } = &msg.b {
-                        did_connection_open = true;
+                    match &msg.b {
+                        to_client::ToClientBody::Init { i: _ } => {
+                            did_connection_open = true;
+                            opened_at = Some(Instant::now());
+                        }
+                        to_client::ToClientBody::Error { e }
+                            if e.ai.is_none()
+                                && !did_connection_open
+                                && FATAL_ERROR_CODES.contains(&e.c.as_str()) =>
+                        {
+                            fatal = Some(ConnectionError::from(e.clone()));
+                        }
+                        _ => {}
                     }
 
                     self.on_message(msg).await;
+
+                    if fatal.is_some() {
+                        break DriverStopReason::ServerError.into();
+                    }
                 }
             }
         };
@@ -164,27 +537,284 @@ impl ActorConnectionInner {
             d.disconnect();
         }
 
+        // Rejected stream requests are only visible once the SSE task ends
+        if let Some(status) = task_end_reason.http_status {
+            if fatal.is_none() && !did_connection_open && !is_retryable_status(status) {
+                fatal = Some(ConnectionError::HttpStatus {
+                    status,
+                    body: String::new(),
+                });
+            }
+        }
+
+        // Fatal errors fail every action once the attempt is over
+        if fatal.is_none() {
+            self.on_connection_lost().await;
+        }
+
         ConnectionAttempt {
-            did_open: did_connection_open,
-            _task_end_reason: task_end_reason,
+            open_for: opened_at.map(|t| t.elapsed()),
+            end_reason: task_end_reason,
+            fatal,
+            params_generation,
+        }
+    }
+
+    /// Applies the [`InFlightPolicy`] to actions sent on the dropped
+    /// connection, they will never be answered.
+    async fn on_connection_lost(self: &Arc<Self>) {
+        let mut resend = Vec::new();
+
+        {
+            let mut in_flight_rpcs = self.in_flight_rpcs.lock().await;
+            let lost: Vec<i64> = in_flight_rpcs
+                .iter()
+                .filter(|(_, action)| action.sent)
+                .map(|(id, _)| *id)
+                .collect();
+
+            for id in lost {
+                let action = in_flight_rpcs.get_mut(&id).unwrap();
+                if self.config.in_flight == InFlightPolicy::ResendIdempotent && action.idempotent {
+                    action.sent = false;
+                    resend.push((id, action.request.clone()));
+                } else if let Some(action) = in_flight_rpcs.remove(&id) {
+                    action.tx.send(Err(Error::ConnectionLost)).ok();
+                }
+            }
         }
+
+        if resend.is_empty() {
+            return;
+        }
+
+        // Re-sent in their original order once reconnected
+        resend.sort_by_key(|(id, _)| *id);
+        self.resend.lock().await.extend(resend.into_iter().map(|(_, msg)| msg));
+    }
+
+    /// Moves the connection to the terminal [`ConnectionStatus::Failed`]
+    /// state, failing every queued & in-flight action with `error`.
+    async fn fail(self: &Arc<Self>, error: ConnectionError) {
+        debug!("Connection failed: {:?}", error);
+
+        // Status first so actions started from here on fail immediately
+        self.set_status(ConnectionStatus::Failed { error: error.clone() }).await;
+        self.ready.send_replace(ReadyState::Failed(error.clone()));
+
+        self.msg_queue.lock().await.clear();
+        self.resend.lock().await.clear();
+        for (_, action) in self.in_flight_rpcs.lock().await.drain() {
+            action.tx.send(Err(error.clone().into())).ok();
+        }
+        self.event_subscriptions.lock().await.clear();
+        self.all_event_streams.lock().await.clear();
+    }
+
+    fn failure(self: &Arc<Self>) -> Option<ConnectionError> {
+        match &*self.status.borrow() {
+            ConnectionStatus::Failed { error } => Some(error.clone()),
+            _ => None,
+        }
+    }
+
+    async fn set_status(self: &Arc<Self>, status: ConnectionStatus) {
+        debug!("Connection status: {:?}", status);
+
+        self.status.send_replace(status.clone());
+
+        for cb in self.status_callbacks.lock().await.iter() {
+            cb(&status);
+        }
+    }
+
+    /// Watches the connection status, starting from the current one.
+    pub fn status(self: &Arc<Self>) -> watch::Receiver<ConnectionStatus> {
+        self.status.subscribe()
+    }
+
+    /// Waits until the connection is open.
+    ///
+    /// Returns the server's error if the handshake failed, e.g. because of
+    /// invalid connection parameters. The connection keeps retrying in the
+    /// background unless the error is fatal, see
+    /// [`ConnectionStatus::Failed`].
+    pub async fn ready(self: &Arc<Self>) -> Result<ConnectionInfo> {
+        let mut ready_rx = self.ready.subscribe();
+        let mut dc_rx = self.dc_watch.0.subscribe();
+
+        tokio::select! {
+            biased;
+            _ = dc_rx.wait_for(|x| *x) => Err(Error::ConnectionDisposed),
+            state = ready_rx.wait_for(|s| !matches!(s, ReadyState::Pending)) => {
+                match &*state.map_err(|_| Error::ConnectionDisposed)? {
+                    ReadyState::Failed(e) => Err(e.clone().into()),
+                    ReadyState::Ready(info) => Ok(info.clone()),
+                    ReadyState::Pending => unreachable!(),
+                }
+            }
+        }
+    }
+
+    /// Current server connection, `None` while not connected.
+    pub fn info(self: &Arc<Self>) -> Option<ConnectionInfo> {
+        match &*self.ready.borrow() {
+            ReadyState::Ready(info) => Some(info.clone()),
+            _ => None,
+        }
+    }
+
+    /// Actor id resolved by the server, `None` while not connected.
+    pub fn actor_id(self: &Arc<Self>) -> Option<String> {
+        self.info().map(|info| info.actor_id)
+    }
+
+    /// Id of the current server connection, `None` while not connected.
+    pub fn connection_id(self: &Arc<Self>) -> Option<String> {
+        self.info().map(|info| info.connection_id)
+    }
+
+    pub fn transport(self: &Arc<Self>) -> TransportKind {
+        self.config.transport_kind
+    }
+
+    pub fn encoding(self: &Arc<Self>) -> EncodingKind {
+        self.config.encoding_kind
+    }
+
+    /// When the current server connection was established, `None` while not
+    /// connected.
+    pub fn connected_since(self: &Arc<Self>) -> Option<SystemTime> {
+        self.info().map(|info| info.connected_since)
+    }
+
+    /// Calls `callback` with errors that are not tied to an action, e.g. a
+    /// rejected handshake, a crashed actor or a panicking event listener.
+    pub async fn on_error<F>(self: &Arc<Self>, callback: F)
+    where
+        F: Fn(&Error) + Send + Sync + 'static,
+    {
+        self.error_callbacks.lock().await.push(Box::new(callback));
+    }
+
+    async fn on_connection_error(self: &Arc<Self>, err: &to_client::Error) {
+        debug!("Connection error: {:?}", err);
+
+        // Errors before the init message belong to the handshake
+        self.ready.send_if_modified(|state| match state {
+            ReadyState::Ready(_) => false,
+            _ => {
+                *state = ReadyState::Failed(err.clone().into());
+                true
+            }
+        });
+
+        self.report_error(&Error::from(err.clone())).await;
+    }
+
+    async fn report_error(self: &Arc<Self>, err: &Error) {
+        for cb in self.error_callbacks.lock().await.iter() {
+            cb(err);
+        }
+    }
+
+    /// Calls `callback` on every status change.
+    pub async fn on_status_change<F>(self: &Arc<Self>, callback: F)
+    where
+        F: Fn(&ConnectionStatus) + Send + Sync + 'static,
+    {
+        self.status_callbacks.lock().await.push(Box::new(callback));
     }
 
     async fn on_open(self: &Arc<Self>, init: &to_client::Init) {
         debug!("Connected to server: {:?}", init);
 
+        self.ready.send_replace(ReadyState::Ready(ConnectionInfo {
+            actor_id: init.ai.clone(),
+            connection_id: init.ci.clone(),
+            transport: self.config.transport_kind,
+            encoding: self.config.encoding_kind,
+            connected_since: SystemTime::now(),
+        }));
+        self.set_status(ConnectionStatus::Connected {
+            actor_id: init.ai.clone(),
+            connection_id: init.ci.clone(),
+        }).await;
+
         for (event_name, _) in self.event_subscriptions.lock().await.iter() {
             self.send_subscription(event_name.clone(), true).await;
         }
 
+        // Re-send lost actions before the ones queued after them
+        let resend: Vec<_> = {
+            let resend = std::mem::take(&mut *self.resend.lock().await);
+            let in_flight_rpcs = self.in_flight_rpcs.lock().await;
+            resend.into_iter()
+                .filter(|msg| match &msg.b {
+                    to_server::ToServerBody::ActionRequest { ar } => in_flight_rpcs.contains_key(&ar.i),
+                    _ => true,
+                })
+                .collect()
+        };
+        for msg in resend {
+            self.send_msg(msg, SendMsgOpts { resend: true, ..Default::default() }).await;
+        }
+
         // Flush message queue
-        for msg in self.msg_queue.lock().await.drain(..) {
+        let (msgs, expired) = self.msg_queue.lock().await.drain();
+        self.fail_dropped(expired).await;
+
+        let msgs: Vec<_> = {
+            // Skip actions whose caller stopped waiting
+            let in_flight_rpcs = self.in_flight_rpcs.lock().await;
+            msgs.into_iter()
+                .filter(|msg| match &msg.b {
+                        to_server::ToServerBody::ActionRequest { ar } => in_flight_rpcs.contains_key(&ar.i),
+                    _ => true,
+                })
+                .collect()
+        };
+
+        for msg in msgs {
             // If its in the queue, it isn't ephemeral, so we pass
             // default SendMsgOpts
             self.send_msg(msg, SendMsgOpts::default()).await;
         }
     }
 
+    /// Fails the actions of messages dropped from the offline queue.
+    async fn fail_dropped(self: &Arc<Self>, dropped: Vec<DroppedMsg>) {
+        if dropped.is_empty() {
+            return;
+        }
+
+        let mut in_flight_rpcs = self.in_flight_rpcs.lock().await;
+        for (msg, error) in dropped {
+            debug!("Dropping queued message: {}", error);
+
+            if let to_server::ToServerBody::ActionRequest { ar } = &msg.b {
+                if let Some(action) = in_flight_rpcs.remove(&ar.i) {
+                    action.tx.send(Err(error)).ok();
+                }
+            }
+        }
+    }
+
+    /// Fails the action of a message the driver could not deliver, other
+    /// messages are reported to [`ActorConnectionInner::on_error`].
+    async fn on_send_failed(self: &Arc<Self>, msg: Arc<to_server::ToServer>, error: Error) {
+        debug!("Failed to deliver message: {}", error);
+
+        if let to_server::ToServerBody::ActionRequest { ar } = &msg.b {
+            if let Some(action) = self.in_flight_rpcs.lock().await.remove(&ar.i) {
+                action.tx.send(Err(error)).ok();
+                return;
+            }
+        }
+
+        self.report_error(&error).await;
+    }
+
     async fn on_message(self: &Arc<Self>, msg: Arc<to_client::ToClient>) {
         let body = &msg.b;
 
@@ -195,31 +825,58 @@ impl ActorConnectionInner {
             to_client::ToClientBody::ActionResponse { ar } => {
                 let id = ar.i;
                 let mut in_flight_rpcs = self.in_flight_rpcs.lock().await;
-                let Some(tx) = in_flight_rpcs.remove(&id) else {
+                let Some(action) = in_flight_rpcs.remove(&id) else {
                     debug!("Unexpected response: rpc id not found");
                     return;
                 };
-                if let Err(e) = tx.send(Ok(ar.clone())) {
+                if let Err(e) = action.tx.send(Ok(ar.clone())) {
                     debug!("{:?}", e);
                     return;
                 }
             }
             to_client::ToClientBody::EventMessage { ev } => {
-                let listeners = self.event_subscriptions.lock().await;
-                if let Some(callbacks) = listeners.get(&ev.n) {
-                    for cb in callbacks {
-                        cb(&ev.a);
+                let mut listeners = Vec::new();
+
+                if let Some(event_listeners) = self.event_subscriptions.lock().await.get(&ev.n) {
+                    listeners.extend(
+                        event_listeners
+                            .iter()
+                            .filter(|l| l.active.load(Ordering::SeqCst))
+                            .map(|l| (Some(l.active.clone()), l.kind.clone())),
+                    );
+                }
+
+                {
+                    let mut all_event_streams = self.all_event_streams.lock().await;
+                    all_event_streams.retain(|tx| !tx.is_closed());
+                    listeners.extend(
+                        all_event_streams
+                            .iter()
+                            .map(|tx| (None, ListenerKind::Stream(tx.clone()))),
+                    );
+                }
+
+                if !listeners.is_empty() {
+                    let dispatch = Dispatch {
+                        event: Event {
+                            name: ev.n.clone(),
+                            args: ev.a.clone(),
+                        },
+                        listeners,
+                    };
+                    if self.dispatch_tx.send(dispatch).is_err() {
+                        debug!("Event dispatcher is not running");
                     }
                 }
             }
             to_client::ToClientBody::Error { e } => {
                 if let Some(action_id) = e.ai {
                     let mut in_flight_rpcs = self.in_flight_rpcs.lock().await;
-                    let Some(tx) = in_flight_rpcs.remove(&action_id) else {
+                    let Some(action) = in_flight_rpcs.remove(&action_id) else {
                         debug!("Unexpected response: rpc id not found");
                         return;
                     };
-                    if let Err(e) = tx.send(Err(e.clone())) {
+                    if let Err(e) = action.tx.send(Err(e.clone().into())) {
                         debug!("{:?}", e);
                         return;
                     }
@@ -227,7 +884,7 @@ impl ActorConnectionInner {
                     return;
                 }
 
-                
+                self.on_connection_error(e).await;
             }
         }
     }
@@ -244,55 +901,111 @@ impl ActorConnectionInner {
                 break 'send_immediately;
             };
 
+            if let to_server::ToServerBody::ActionRequest { ar } = &msg.b {
+                if let Some(action) = self.in_flight_rpcs.lock().await.get_mut(&ar.i) {
+                    action.sent = true;
+                }
+            }
+
             return;
         }
 
         // Otherwise queue
-        if opts.ephemeral == false {
-            self.msg_queue.lock().await.push(msg.clone());
+        if opts.resend {
+            self.resend.lock().await.push(msg);
+        } else if opts.ephemeral == false {
+            let dropped = self.msg_queue.lock().await.push(msg.clone());
+            self.fail_dropped(dropped).await;
         }
 
         return;
     }
 
     pub async fn action(self: &Arc<Self>, method: &str, params: Vec<Value>) -> Result<Value> {
+        self.action_with_options(method, params, ActionOptions::default()).await
+    }
+
+    /// Like [`ActorConnectionInner::action`], bounded by the timeout &
+    /// cancellation token of `opts`. Headers are ignored since the action is
+    /// sent over the connection.
+    ///
+    /// If the connection drops before the response arrives the
+    /// [`InFlightPolicy`] decides whether the action fails or is sent again.
+    pub async fn action_with_options(
+        self: &Arc<Self>,
+        method: &str,
+        params: Vec<Value>,
+        opts: ActionOptions,
+    ) -> Result<Value> {
         let id: i64 = self.rpc_counter.fetch_add(1, Ordering::SeqCst);
+        let started = Instant::now();
 
-        let (tx, rx) = oneshot::channel();
-        self.in_flight_rpcs.lock().await.insert(id, tx);
+        let res = opts.run(self.send_action(id, method, params, opts.idempotent)).await;
+        telemetry::action_completed(method, self.config.transport_kind.as_str(), started.elapsed());
 
-        self.send_msg(
-            Arc::new(to_server::ToServer {
-                b: to_server::ToServerBody::ActionRequest {
-                    ar: to_server::ActionRequest {
-                        i: id,
-                        n: method.to_string(),
-                        a: params,
-                    },
+        if let Err(Error::Timeout | Error::Cancelled) = &res {
+            // Nobody is waiting for the response anymore
+            self.in_flight_rpcs.lock().await.remove(&id);
+        }
+
+        res
+    }
+
+    async fn send_action(
+        self: &Arc<Self>,
+        id: i64,
+        method: &str,
+        params: Vec<Value>,
+        idempotent: bool,
+    ) -> Result<Value> {
+        let request = Arc::new(to_server::ToServer {
+            b: to_server::ToServerBody::ActionRequest {
+                ar: to_server::ActionRequest {
+                    i: id,
+                    n: method.to_string(),
+                    a: params,
                 },
-            }),
-            SendMsgOpts::default(),
-        )
-        .await;
+            },
+        });
+
+        let (tx, rx) = oneshot::channel();
+        self.in_flight_rpcs.lock().await.insert(id, InFlightAction {
+            tx,
+            request: request.clone(),
+            idempotent,
+            sent: false,
+            _gauge: InFlightGauge::start(),
+        });
+
+        // Checked after registering, `fail` drains in-flight actions after
+        // updating the status
+        if let Some(error) = self.failure() {
+            self.in_flight_rpcs.lock().await.remove(&id);
+            return Err(error.into());
+        }
+
+        self.send_msg(request, SendMsgOpts::default()).await;
 
         let Ok(res) = rx.await else {
-            // Verbosity
-            return Err(anyhow::anyhow!("Socket closed during rpc"));
+            // In-flight rpcs are dropped when the connection is disposed
+            return Err(Error::ConnectionDisposed);
         };
 
-        match res {
-            Ok(ok) => Ok(ok.o),
-            Err(err) => {
-                let metadata = err.md.unwrap_or(Value::Null);
-
-                Err(anyhow::anyhow!(
-                    "RPC Error({}): {:?}, {:#}",
-                    err.c,
-                    err.m,
-                    metadata
-                ))
-            }
-        }
+        Ok(res?.o)
+    }
+
+    /// Typed variant of [`ActorConnectionInner::action`].
+    ///
+    /// `args` is serialized as the positional argument array, so pass a tuple
+    /// (e.g. `("william", 1)`, or `()` for no arguments).
+    pub async fn action_typed<A, R>(self: &Arc<Self>, method: &str, args: A) -> Result<R>
+    where
+        A: Serialize,
+        R: DeserializeOwned,
+    {
+        let output = self.action(method, serialize_args(args)?).await?;
+
+        deserialize_value(output)
     }
 
     async fn send_subscription(self: &Arc<Self>, event_name: String, subscribe: bool) {
@@ -305,7 +1018,7 @@ impl ActorConnectionInner {
                     },
                 },
             }),
-            SendMsgOpts { ephemeral: true },
+            SendMsgOpts { ephemeral: true, ..Default::default() },
         )
         .await;
     }
@@ -313,31 +1026,186 @@ impl ActorConnectionInner {
     async fn add_event_subscription(
         self: &Arc<Self>,
         event_name: String,
-        callback: Box<EventCallback>,
-    ) {
-        // TODO: Support for once
+        kind: ListenerKind,
+    ) -> Subscription {
+        let id = self.listener_counter.fetch_add(1, Ordering::SeqCst);
+        let active = Arc::new(AtomicBool::new(true));
+
         let mut listeners = self.event_subscriptions.lock().await;
 
-        let is_new_subscription = listeners.contains_key(&event_name) == false;
+        let is_new_subscription = !listeners.contains_key(&event_name);
 
         listeners
             .entry(event_name.clone())
-            .or_insert(Vec::new())
-            .push(callback);
+            .or_default()
+            .push(EventListener {
+                id,
+                active: active.clone(),
+                kind,
+            });
 
         if is_new_subscription {
-            self.send_subscription(event_name, true).await;
+            self.send_subscription(event_name.clone(), true).await;
+        }
+
+        Subscription {
+            conn: Some(Arc::downgrade(self)),
+            event_name,
+            id,
+            active,
         }
     }
 
-    pub async fn on_event<F>(self: &Arc<Self>, event_name: &str, callback: F)
+    async fn remove_event_listener(self: &Arc<Self>, event_name: &str, id: u64) {
+        let mut listeners = self.event_subscriptions.lock().await;
+
+        let Some(event_listeners) = listeners.get_mut(event_name) else {
+            return;
+        };
+        event_listeners.retain(|l| l.id != id);
+
+        if event_listeners.is_empty() {
+            listeners.remove(event_name);
+            self.send_subscription(event_name.to_string(), false).await;
+        }
+    }
+
+    /// Calls `callback` with the arguments of every `event_name` event.
+    ///
+    /// Runs on the dispatcher task, see
+    /// [`on_event_async`](Self::on_event_async).
+    pub async fn on_event<F>(self: &Arc<Self>, event_name: &str, callback: F) -> Subscription
     where
         F: Fn(&Vec<Value>) + Send + Sync + 'static,
     {
-        self.add_event_subscription(event_name.to_string(), Box::new(callback))
+        self.add_event_subscription(event_name.to_string(), ListenerKind::Callback(Arc::new(callback)))
             .await
     }
 
+    /// Async variant of [`ActorConnectionInner::on_event`].
+    ///
+    /// Listeners run one at a time on the connection's dispatcher task, each
+    /// event is only delivered once the previous one has been handled. They
+    /// may subscribe & unsubscribe, panics are reported through
+    /// [`on_error`](Self::on_error).
+    pub async fn on_event_async<F, Fut>(self: &Arc<Self>, event_name: &str, callback: F) -> Subscription
+    where
+        F: Fn(Event) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let callback: Arc<AsyncEventCallback> = Arc::new(move |event| callback(event).boxed());
+
+        self.add_event_subscription(event_name.to_string(), ListenerKind::AsyncCallback(callback))
+            .await
+    }
+
+    /// Stream of `event_name` occurrences, see [`EventStream`].
+    pub async fn events(self: &Arc<Self>, event_name: &str) -> EventStream {
+        self.events_with_options(event_name, EventStreamOptions::default())
+            .await
+    }
+
+    pub async fn events_with_options(
+        self: &Arc<Self>,
+        event_name: &str,
+        options: EventStreamOptions,
+    ) -> EventStream {
+        let (tx, mut stream) = EventStream::channel(options);
+
+        let subscription = self
+            .add_event_subscription(event_name.to_string(), ListenerKind::Stream(Arc::new(tx)))
+            .await;
+        stream.set_subscription(subscription);
+
+        stream
+    }
+
+    /// Stream of every event received by the connection.
+    ///
+    /// Does not subscribe to anything, so it only yields events subscribed
+    /// through [`on_event`](Self::on_event) or [`events`](Self::events).
+    pub async fn all_events(self: &Arc<Self>) -> EventStream {
+        self.all_events_with_options(EventStreamOptions::default())
+            .await
+    }
+
+    pub async fn all_events_with_options(self: &Arc<Self>, options: EventStreamOptions) -> EventStream {
+        let (tx, stream) = EventStream::channel(options);
+
+        if !self.is_disconnecting() && self.failure().is_none() {
+            self.all_event_streams.lock().await.push(Arc::new(tx));
+        }
+
+        stream
+    }
+
+    /// Waits for the next occurrence of `event_name`, returning its
+    /// arguments.
+    pub async fn once(self: &Arc<Self>, event_name: &str) -> Result<Vec<Value>> {
+        let (tx, rx) = oneshot::channel();
+        let tx = std::sync::Mutex::new(Some(tx));
+
+        let _subscription = self.on_event(event_name, move |args| {
+            if let Some(tx) = tx.lock().ok().and_then(|mut tx| tx.take()) {
+                tx.send(args.clone()).ok();
+            }
+        }).await;
+
+        // Checked after subscribing, listeners are cleared once the
+        // connection is disposed or failed
+        if let Some(error) = self.failure() {
+            return Err(error.into());
+        }
+        if self.is_disconnecting() {
+            return Err(Error::ConnectionDisposed);
+        }
+
+        match rx.await {
+            Ok(args) => Ok(args),
+            Err(_) => Err(self.failure().map(Error::from).unwrap_or(Error::ConnectionDisposed)),
+        }
+    }
+
+    /// Typed variant of [`ActorConnectionInner::on_event`].
+    ///
+    /// The event arguments are deserialized into `T` (e.g. a tuple matching
+    /// the arguments), failures are passed to the callback as
+    /// [`Error::Deserialization`].
+    pub async fn on_event_typed<T, F>(self: &Arc<Self>, event_name: &str, callback: F) -> Subscription
+    where
+        T: DeserializeOwned,
+        F: Fn(Result<T>) + Send + Sync + 'static,
+    {
+        self.on_event(event_name, move |args| {
+            callback(deserialize_value(Value::Array(args.clone())))
+        })
+        .await
+    }
+
+    /// Replaces the connection parameters, e.g. after an auth token rotated.
+    ///
+    /// Reconnects right away with the new parameters as a new server
+    /// connection. Event subscriptions and queued actions carry over, actions
+    /// in flight are handled by the [`InFlightPolicy`]. Use
+    /// [`ActorConnectionInner::ready`] to wait for the new handshake.
+    pub async fn set_params(self: &Arc<Self>, params: Option<Value>) {
+        if self.is_disconnecting() || self.failure().is_some() {
+            return;
+        }
+
+        {
+            let mut parameters = self.parameters.lock().await;
+            *parameters = params;
+            self.params_generation.fetch_add(1, Ordering::SeqCst);
+        }
+
+        self.ready.send_replace(ReadyState::Pending);
+        match self.driver.lock().await.deref() {
+            Some(d) => d.disconnect(),
+            None => self.reconnect_now.notify_one(),
+        }
+    }
+
     pub async fn disconnect(self: &Arc<Self>) {
         if self.is_disconnecting() {
             // We are already disconnecting
@@ -353,6 +1221,7 @@ impl ActorConnectionInner {
         }
         self.in_flight_rpcs.lock().await.clear();
         self.event_subscriptions.lock().await.clear();
+        self.all_event_streams.lock().await.clear();
         let Some(rx) = self.disconnection_rx.lock().await.take() else {
             return;
         };
@@ -368,6 +1237,11 @@ pub fn start_connection(
 ) {
     let (tx, rx) = oneshot::channel();
 
+    let dispatch_rx = conn.dispatch_rx.lock().ok().and_then(|mut rx| rx.take());
+    if let Some(dispatch_rx) = dispatch_rx {
+        tokio::spawn(dispatch_events(Arc::downgrade(conn), dispatch_rx));
+    }
+
     let conn = conn.clone();
 
     tokio::spawn(async move {
@@ -382,39 +1256,100 @@ pub fn start_connection(
             *stop_rx = Some(rx);
         }
 
-        'keepalive: loop {
-            debug!("Attempting to reconnect");
-            let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(30));
-            let mut retry_attempt = 0;
-            'retry: loop {
-                retry_attempt += 1;
-                debug!(
-                    "Establish conn: attempt={}, timeout={:?}",
-                    retry_attempt,
-                    backoff.delay()
-                );
-                let attempt = conn.try_connect().await;
-
-                if conn.is_disconnecting() {
-                    break 'keepalive;
+        let policy = &conn.config.reconnect;
+        let mut backoff = Backoff::from_policy(policy);
+        // Failed attempts & time since the connection was last healthy
+        let mut reconnect_attempt: u32 = 0;
+        let mut healthy_at = Instant::now();
+
+        let fatal = loop {
+            debug!("Establish conn: attempt={}", reconnect_attempt + 1);
+            let attempt = conn.try_connect().await;
+
+            if conn.is_disconnecting() {
+                break None;
+            }
+
+            if let Some(error) = attempt.fatal {
+                break Some(error);
+            }
+
+            if conn.params_generation.load(Ordering::SeqCst) != attempt.params_generation {
+                debug!("Reconnecting with new parameters");
+                if attempt.open_for.is_some() {
+                    conn.set_status(ConnectionStatus::Disconnected {
+                        reason: attempt.end_reason,
+                    }).await;
                 }
+                backoff.reset();
+                reconnect_attempt = 0;
+                healthy_at = Instant::now();
+                // Not needed anymore if set while connecting
+                conn.reconnect_now.notified().now_or_never();
+                continue;
+            }
 
-                if attempt.did_open {
-                    break 'retry;
+            if let Some(open_for) = attempt.open_for {
+                conn.ready.send_replace(ReadyState::Pending);
+                conn.set_status(ConnectionStatus::Disconnected {
+                    reason: attempt.end_reason.clone(),
+                }).await;
+
+                if open_for >= policy.healthy_after {
+                    // Start over after a healthy connection, still jittered
+                    // so dropped clients do not reconnect in lockstep
+                    backoff.reset();
+                    reconnect_attempt = 0;
+                    healthy_at = Instant::now();
+                }
+            }
+
+            reconnect_attempt += 1;
+            let ctx = RetryContext {
+                attempt: reconnect_attempt,
+                elapsed: healthy_at.elapsed(),
+                delay: backoff.next_delay(),
+                reason: attempt.end_reason,
+            };
+            let delay = match policy.decide(&ctx) {
+                RetryDecision::Retry => ctx.delay,
+                RetryDecision::RetryAfter(delay) => delay,
+                RetryDecision::GiveUp => {
+                    debug!("Giving up reconnecting: {:?}", ctx);
+                    break Some(ConnectionError::RetriesExhausted {
+                        attempts: reconnect_attempt - 1,
+                    });
                 }
+            };
+            telemetry::reconnect(ctx.reason.stop_reason);
 
-                let mut dc_rx = conn.dc_watch.0.subscribe();
+            conn.set_status(ConnectionStatus::Reconnecting {
+                attempt: reconnect_attempt,
+                next_delay: delay,
+            }).await;
 
-                tokio::select! {
-                    _ = backoff.tick() => {},
-                    _ = dc_rx.wait_for(|x| *x == true) => {
-                        break 'keepalive;
-                    }
-                    _ = shutdown_rx.recv() => {
-                        debug!("Received shutdown signal, stopping connection attempts");
-                        break 'keepalive;
-                    }
+            let mut dc_rx = conn.dc_watch.0.subscribe();
+
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {},
+                _ = conn.reconnect_now.notified() => {},
+                _ = dc_rx.wait_for(|x| *x == true) => {
+                    break None;
                 }
+                _ = shutdown_rx.recv() => {
+                    debug!("Received shutdown signal, stopping connection attempts");
+                    break None;
+                }
+            }
+        };
+
+        match fatal {
+            Some(error) => conn.fail(error).await,
+            None => {
+                conn.ready.send_replace(ReadyState::Pending);
+                conn.set_status(ConnectionStatus::Disconnected {
+                    reason: DriverStopReason::UserAborted.into(),
+                }).await;
             }
         }
 
@@ -426,9 +1361,9 @@ pub fn start_connection(
 impl Debug for ActorConnectionInner {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("ActorConnection")
-            .field("endpoint", &self.endpoint)
-            .field("transport_kind", &self.transport_kind)
-            .field("encoding_kind", &self.encoding_kind)
+            .field("endpoint", &self.config.endpoint)
+            .field("transport_kind", &self.config.transport_kind)
+            .field("encoding_kind", &self.config.encoding_kind)
             .finish()
     }
 }
\ No newline at end of file
@@ -0,0 +1,121 @@
+use std::fmt;
+
+use futures_util::future::BoxFuture;
+use serde_json::Value as JsonValue;
+
+use crate::{
+    client::ClientConfig,
+    error::{Error, Result},
+    protocol::query::ActorQuery,
+};
+
+/// Request a [`CredentialProvider`] is asked credentials for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestKind {
+    /// Stateless action.
+    Action,
+    /// `/actors/resolve`, also used to create actors.
+    Resolve,
+    /// Message posted to an SSE connection, only headers are used.
+    Message,
+    /// WebSocket or SSE connection attempt, including reconnects.
+    Connect,
+    /// Raw HTTP request or WebSocket.
+    Raw,
+}
+
+#[derive(Debug, Clone)]
+pub struct RequestContext {
+    pub kind: RequestKind,
+    /// Actor the request is for.
+    pub query: ActorQuery,
+}
+
+/// Supplies connection parameters & headers before every request, e.g. to
+/// refresh short-lived tokens.
+///
+/// Errors fail the request with [`Error::Auth`] and are not retried. A
+/// connection attempt that fails moves the connection to
+/// [`ConnectionStatus::Failed`](crate::ConnectionStatus::Failed), a failed SSE
+/// message only rejects its action.
+pub trait CredentialProvider: Send + Sync {
+    /// Connection parameters for the request. `None` keeps the parameters
+    /// the handle or connection was created with.
+    fn params<'a>(&'a self, ctx: &'a RequestContext) -> BoxFuture<'a, Result<Option<JsonValue>>>;
+
+    /// Extra headers for the request.
+    fn headers<'a>(&'a self, _ctx: &'a RequestContext) -> BoxFuture<'a, Result<Vec<(String, String)>>> {
+        Box::pin(async { Ok(Vec::new()) })
+    }
+}
+
+impl fmt::Debug for dyn CredentialProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CredentialProvider")
+    }
+}
+
+/// Parameters & headers to send with a request.
+#[derive(Debug, Default)]
+pub(crate) struct Credentials {
+    pub params: Option<JsonValue>,
+    pub headers: Vec<(String, String)>,
+}
+
+impl Credentials {
+    pub fn header_pairs(&self) -> impl Iterator<Item = (&str, String)> {
+        self.headers.iter().map(|(k, v)| (k.as_str(), v.clone()))
+    }
+}
+
+fn auth_error(err: Error) -> Error {
+    match err {
+        Error::Auth(message) => Error::Auth(message),
+        err => Error::Auth(err.to_string()),
+    }
+}
+
+/// Asks the client's provider for credentials, falling back to `params`.
+pub(crate) async fn credentials(
+    config: &ClientConfig,
+    kind: RequestKind,
+    query: &ActorQuery,
+    params: Option<&JsonValue>,
+) -> Result<Credentials> {
+    let Some(provider) = &config.credentials else {
+        return Ok(Credentials {
+            params: params.cloned(),
+            headers: Vec::new(),
+        });
+    };
+
+    let ctx = RequestContext {
+        kind,
+        query: query.clone(),
+    };
+    let provided = provider.params(&ctx).await.map_err(auth_error)?;
+    let headers = provider.headers(&ctx).await.map_err(auth_error)?;
+
+    Ok(Credentials {
+        params: provided.or_else(|| params.cloned()),
+        headers,
+    })
+}
+
+/// Asks the client's provider for headers only.
+pub(crate) async fn credential_headers(
+    config: &ClientConfig,
+    kind: RequestKind,
+    query: &ActorQuery,
+) -> Result<Vec<(String, String)>> {
+    let Some(provider) = &config.credentials else {
+        return Ok(Vec::new());
+    };
+
+    let ctx = RequestContext {
+        kind,
+        query: query.clone(),
+    };
+
+    provider.headers(&ctx).await.map_err(auth_error)
+}
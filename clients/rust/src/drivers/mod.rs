@@ -1,10 +1,11 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use crate::{
+    client::ClientConfig,
+    error::{Error, Result},
     protocol::{query, to_client, to_server},
-    EncodingKind, TransportKind
+    TransportKind
 };
-use anyhow::Result;
 use serde_json::Value;
 use tokio::{
     sync::mpsc,
@@ -18,12 +19,75 @@ pub mod ws;
 pub type MessageToClient = Arc<to_client::ToClient>;
 pub type MessageToServer = Arc<to_server::ToServer>;
 
+/// Sent by a driver to its connection.
+#[derive(Debug)]
+pub enum DriverEvent {
+    Message(MessageToClient),
+    /// The message could not be delivered, e.g. the SSE message request was
+    /// rejected.
+    SendFailed(MessageToServer, Error),
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DriverStopReason {
     UserAborted,
     ServerDisconnect,
     ServerError,
     TaskError,
+    /// The server stopped answering pings, or the SSE stream went silent.
+    HeartbeatTimeout,
+}
+
+/// Detection of dead connections, e.g. a half-open TCP connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeartbeatPolicy {
+    /// How often to ping the server over WebSockets, `None` disables pings.
+    pub ping_interval: Option<Duration>,
+    /// How long to wait for the server to answer a ping.
+    pub pong_timeout: Duration,
+    /// How long an SSE stream may go without events or comments, `None`
+    /// waits forever.
+    pub sse_idle_timeout: Option<Duration>,
+}
+
+impl Default for HeartbeatPolicy {
+    fn default() -> Self {
+        Self {
+            ping_interval: Some(Duration::from_secs(30)),
+            pong_timeout: Duration::from_secs(10),
+            sse_idle_timeout: None,
+        }
+    }
+}
+
+/// Sleeps until `deadline`, forever if there is none.
+pub(crate) async fn sleep_until(deadline: Option<tokio::time::Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Why a driver stopped, with the WebSocket close frame if the server sent
+/// one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisconnectReason {
+    pub stop_reason: DriverStopReason,
+    pub close_code: Option<u16>,
+    pub close_reason: Option<String>,
+    /// Status of a rejected SSE stream request.
+    pub http_status: Option<u16>,
+}
+
+impl From<DriverStopReason> for DisconnectReason {
+    fn from(stop_reason: DriverStopReason) -> Self {
+        Self {
+            stop_reason,
+            close_code: None,
+            close_reason: None,
+            http_status: None,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -41,7 +105,10 @@ impl DriverHandle {
     }
 
     pub async fn send(&self, msg: Arc<to_server::ToServer>) -> Result<()> {
-        self.sender.send(msg).await?;
+        self.sender
+            .send(msg)
+            .await
+            .map_err(|_| Error::ConnectionDisposed)?;
 
         Ok(())
     }
@@ -60,15 +127,16 @@ impl Drop for DriverHandle {
 
 pub type DriverConnection = (
     DriverHandle,
-    mpsc::Receiver<MessageToClient>,
-    JoinHandle<DriverStopReason>,
+    mpsc::Receiver<DriverEvent>,
+    JoinHandle<DisconnectReason>,
 );
 
 pub struct DriverConnectArgs {
-    pub endpoint: String,
-    pub encoding_kind: EncodingKind,
+    pub config: Arc<ClientConfig>,
     pub query: query::ActorQuery,
     pub parameters: Option<Value>,
+    /// Headers from the [`CredentialProvider`](crate::CredentialProvider).
+    pub headers: Vec<(String, String)>,
 }
 
 pub async fn connect_driver(
@@ -1,19 +1,24 @@
-use anyhow::{Result};
 use base64::prelude::*;
 use eventsource_client::{BoxStream, Client, ClientBuilder, ReconnectOptionsBuilder, SSE};
 use futures_util::StreamExt;
 use reqwest::header::USER_AGENT;
 use std::sync::Arc;
 use tokio::sync::mpsc;
+use tokio::time::Instant;
 use tracing::debug;
 
 use crate::{
-    common::{EncodingKind, HEADER_ACTOR_ID, HEADER_ACTOR_QUERY, HEADER_CONN_ID, HEADER_CONN_PARAMS, HEADER_CONN_TOKEN, HEADER_ENCODING, USER_AGENT_VALUE},
-    protocol::{to_client, to_server}
+    error::{Error, Result},
+    client::ClientConfig,
+    common::{decode_response_error, EncodingKind, ProtocolHeader},
+    credentials::{credential_headers, RequestKind},
+    protocol::query::{ActorQuery, GetForIdRequest},
+    protocol::{to_client, to_server},
+    telemetry,
 };
 
 use super::{
-    DriverConnectArgs, DriverConnection, DriverHandle, DriverStopReason, MessageToClient, MessageToServer
+    sleep_until, DisconnectReason, DriverConnectArgs, DriverConnection, DriverEvent, DriverHandle, DriverStopReason, MessageToServer
 };
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -27,33 +32,45 @@ struct ConnectionDetails {
 struct Context {
     conn: ConnectionDetails,
     encoding_kind: EncodingKind,
-    endpoint: String,
+    config: Arc<ClientConfig>,
 }
 
 pub(crate) async fn connect(args: DriverConnectArgs) -> Result<DriverConnection> {
-    let endpoint = format!("{}/actors/connect/sse", args.endpoint);
+    let endpoint = format!("{}/actors/connect/sse", args.config.endpoint);
 
     let params_string = match args.parameters {
         Some(p) => Some(serde_json::to_string(&p)).transpose(),
         None => Ok(None),
-    }?;
+    }.map_err(Error::serialization)?;
+    let query_string = serde_json::to_string(&args.query).map_err(Error::serialization)?;
+
+    let config = &args.config;
+    let mut protocol_headers = Vec::new();
+    protocol_headers.extend(config.protocol_headers(ProtocolHeader::Encoding, config.encoding_kind.as_str()));
+    protocol_headers.extend(config.protocol_headers(ProtocolHeader::ActorQuery, &query_string));
+    if let Some(p) = &params_string {
+        protocol_headers.extend(config.protocol_headers(ProtocolHeader::ConnParams, p));
+    }
 
-    let client = ClientBuilder::for_url(&endpoint)?
-        .header(USER_AGENT.as_str(), USER_AGENT_VALUE)?
-        .header(HEADER_ENCODING, args.encoding_kind.as_str())?
-        .header(HEADER_ACTOR_QUERY, serde_json::to_string(&args.query)?.as_str())?;
+    let mut client = ClientBuilder::for_url(&endpoint)?
+        .header(USER_AGENT.as_str(), &config.user_agent)?;
+
+    for (key, value) in &protocol_headers {
+        client = client.header(key, value)?;
+    }
+
+    for (key, value) in config.headers.iter().chain(&args.headers) {
+        client = client.header(key, value)?;
+    }
 
-    let client = match params_string {
-        Some(p) => client.header(HEADER_CONN_PARAMS, p.as_str())?,
-        None => client,
-    };
     let client = client.reconnect(ReconnectOptionsBuilder::new(false).build())
         .build();
 
-    let (in_tx, in_rx) = mpsc::channel::<MessageToClient>(32);
-    let (out_tx, out_rx) = mpsc::channel::<MessageToServer>(32);
+    let capacity = args.config.channel_capacity;
+    let (in_tx, in_rx) = mpsc::channel::<DriverEvent>(capacity);
+    let (out_tx, out_rx) = mpsc::channel::<MessageToServer>(capacity);
 
-    let task = tokio::spawn(start(client, args.endpoint, args.encoding_kind, in_tx, out_rx));
+    let task = tokio::spawn(start(client, args.config, in_tx, out_rx));
 
     let handle = DriverHandle::new(out_tx, task.abort_handle());
     Ok((handle, in_rx, task))
@@ -65,23 +82,51 @@ async fn sse_send_msg(ctx: &Context, msg: MessageToServer) -> Result<String> {
     // Add connection ID and token to the request URL
     let request_url = format!(
         "{}/actors/message",
-        ctx.endpoint
+        ctx.config.endpoint
     );
 
-    let res = reqwest::Client::new()
-        .post(request_url)
+    let mut req = ctx.config.http_client.post(request_url);
+
+    if let Some(timeout) = ctx.config.request_timeout {
+        req = req.timeout(timeout);
+    }
+
+    for (key, value) in &ctx.config.headers {
+        req = req.header(key, value);
+    }
+
+    let query = ActorQuery::GetForId {
+        get_for_id: GetForIdRequest {
+            actor_id: ctx.conn.actor_id.clone(),
+        },
+    };
+    for (key, value) in credential_headers(&ctx.config, RequestKind::Message, &query).await? {
+        req = req.header(key, value);
+    }
+
+    let config = &ctx.config;
+    let protocol_headers = config
+        .protocol_headers(ProtocolHeader::Encoding, ctx.encoding_kind.as_str())
+        .chain(config.protocol_headers(ProtocolHeader::ActorId, &ctx.conn.actor_id))
+        .chain(config.protocol_headers(ProtocolHeader::ConnId, &ctx.conn.id))
+        .chain(config.protocol_headers(ProtocolHeader::ConnToken, &ctx.conn.token));
+    for (key, value) in protocol_headers {
+        req = req.header(key, value);
+    }
+
+    let len = msg.len();
+    let res = req
         .body(msg)
-        .header(USER_AGENT, USER_AGENT_VALUE)
-        .header(HEADER_ENCODING, ctx.encoding_kind.as_str())
-        .header(HEADER_ACTOR_ID, ctx.conn.actor_id.as_str())
-        .header(HEADER_CONN_ID, ctx.conn.id.as_str())
-        .header(HEADER_CONN_TOKEN, ctx.conn.token.as_str())
+        .header(USER_AGENT, &ctx.config.user_agent)
         .send()
         .await?;
+    telemetry::message_sent(ctx.encoding_kind, len);
 
 
     if !res.status().is_success() {
-        return Err(anyhow::anyhow!("Failed to send message: {:?}", res));
+        let status = res.status().as_u16();
+        let body = res.bytes().await?;
+        return Err(decode_response_error(status, &body, ctx.encoding_kind));
     }
 
     let res = res.text().await?;
@@ -91,36 +136,60 @@ async fn sse_send_msg(ctx: &Context, msg: MessageToServer) -> Result<String> {
 
 async fn start(
     client: impl Client,
-    endpoint: String,
-    encoding_kind: EncodingKind,
-    in_tx: mpsc::Sender<MessageToClient>,
+    config: Arc<ClientConfig>,
+    in_tx: mpsc::Sender<DriverEvent>,
     mut out_rx: mpsc::Receiver<MessageToServer>,
-) -> DriverStopReason {
+) -> DisconnectReason {
+    let encoding_kind = config.encoding_kind;
     let mut stream = client.stream();
 
+    let handshake = do_handshake(&mut stream, encoding_kind, &in_tx);
+    let handshake = match config.connect_timeout {
+        Some(timeout) => match tokio::time::timeout(timeout, handshake).await {
+            Ok(res) => res,
+            Err(_) => {
+                debug!("Sse handshake timed out");
+                return DriverStopReason::TaskError.into();
+            }
+        },
+        None => handshake.await,
+    };
+
     let ctx = Context {
-        conn: match do_handshake(&mut stream, encoding_kind, &in_tx).await {
+        conn: match handshake {
             Ok(conn) => conn,
             Err(reason) => return reason
         },
         encoding_kind,
-        endpoint,
+        config,
     };
 
     debug!("Handshake completed successfully");
 
+    let idle_timeout = ctx.config.heartbeat.sse_idle_timeout;
+    let mut idle_deadline = idle_timeout.map(|timeout| Instant::now() + timeout);
+
     loop {
         tokio::select! {
+            _ = sleep_until(idle_deadline) => {
+                debug!("Sse stream idle for too long");
+                return DriverStopReason::HeartbeatTimeout.into();
+            },
             // Handle outgoing messages
             msg = out_rx.recv() => {
                 let Some(msg) = msg else {
-                    return DriverStopReason::UserAborted;
+                    return DriverStopReason::UserAborted.into();
                 };
 
-                let res = match sse_send_msg(&ctx, msg).await {
+                let res = match sse_send_msg(&ctx, msg.clone()).await {
                     Ok(res) => res,
                     Err(e) => {
                         debug!("Failed to send message: {:?}", e);
+                        // The connection fails the action, it is not retried
+                        if let Err(e) = in_tx.send(DriverEvent::SendFailed(msg, e)).await {
+                            debug!("Receiver in_rx dropped {:?}", e);
+                            return DriverStopReason::UserAborted.into();
+                        }
                         continue;
                     }
                 };
@@ -130,31 +199,38 @@ async fn start(
             msg = stream.next() => {
                 let Some(msg) = msg else {
                     // Receiver dropped
-                    return DriverStopReason::ServerDisconnect;
+                    return DriverStopReason::ServerDisconnect.into();
                 };
 
+                // Events & keep-alive comments both count as activity
+                if let Some(timeout) = idle_timeout {
+                    idle_deadline = Some(Instant::now() + timeout);
+                }
+
                 match msg {
                     Ok(msg) => match msg {
                         SSE::Comment(comment) => debug!("Sse comment: {}", comment),
                         SSE::Connected(_) => debug!("warning: received sse connection past-handshake"),
                         SSE::Event(event) => {
+                            telemetry::message_received(encoding_kind, event.data.len());
                             let msg = match deserialize(encoding_kind, &event.data) {
                                 Ok(msg) => msg,
                                 Err(e) => {
                                     debug!("Failed to deserialize {:?} {:?}", event, e);
+                                    telemetry::deserialization_failed(encoding_kind);
                                     continue;
                                 }
                             };
 
-                            if let Err(e) = in_tx.send(Arc::new(msg)).await {
+                            if let Err(e) = in_tx.send(DriverEvent::Message(Arc::new(msg))).await {
                                 debug!("Receiver in_rx dropped {:?}", e);
-                                return DriverStopReason::UserAborted;
+                                return DriverStopReason::UserAborted.into();
                             }
                         },
                     }
                     Err(e) => {
                         debug!("Sse error: {}", e);
-                        return DriverStopReason::ServerError;
+                        return DriverStopReason::ServerError.into();
                     }
                 }
             }
@@ -165,15 +241,15 @@ async fn start(
 async fn do_handshake(
     stream: &mut BoxStream<eventsource_client::Result<SSE>>,
     encoding_kind: EncodingKind,
-    in_tx: &mpsc::Sender<MessageToClient>,
-) -> Result<ConnectionDetails, DriverStopReason> {
+    in_tx: &mpsc::Sender<DriverEvent>,
+) -> Result<ConnectionDetails, DisconnectReason> {
     loop {
         tokio::select! {
             // Handle sse incoming
             msg = stream.next() => {
                 let Some(msg) = msg else {
                     debug!("Receiver dropped");
-                    return Err(DriverStopReason::ServerDisconnect);
+                    return Err(DriverStopReason::ServerDisconnect.into());
                 };
 
                 match msg {
@@ -181,19 +257,21 @@ async fn do_handshake(
                         SSE::Comment(comment) => debug!("Sse comment {:?}", comment),
                         SSE::Connected(_) => debug!("Connected Sse"),
                         SSE::Event(event) => {
+                            telemetry::message_received(encoding_kind, event.data.len());
                             let msg = match deserialize(encoding_kind, &event.data) {
                                 Ok(msg) => msg,
                                 Err(e) => {
                                     debug!("Failed to deserialize {:?} {:?}", event, e);
+                                    telemetry::deserialization_failed(encoding_kind);
                                     continue;
                                 }
                             };
 
                             let msg = Arc::new(msg);
 
-                            if let Err(e) = in_tx.send(msg.clone()).await {
+                            if let Err(e) = in_tx.send(DriverEvent::Message(msg.clone())).await {
                                 debug!("Receiver in_rx dropped {:?}", e);
-                                return Err(DriverStopReason::UserAborted);
+                                return Err(DriverStopReason::UserAborted.into());
                             }
 
                             // Wait until we get an Init packet
@@ -210,8 +288,15 @@ async fn do_handshake(
                         },
                     }
                     Err(e) => {
-                        eprintln!("Sse error: {}", e);
-                        return Err(DriverStopReason::ServerError);
+                        debug!("Sse error: {}", e);
+                        let http_status = match &e {
+                            eventsource_client::Error::UnexpectedResponse(res, _) => Some(res.status()),
+                            _ => None,
+                        };
+                        return Err(DisconnectReason {
+                            http_status,
+                            ..DriverStopReason::ServerError.into()
+                        });
                     }
                 }
             }
@@ -222,22 +307,20 @@ async fn do_handshake(
 fn deserialize(encoding_kind: EncodingKind, msg: &str) -> Result<to_client::ToClient> {
     match encoding_kind {
         EncodingKind::Json => {
-            Ok(serde_json::from_str::<to_client::ToClient>(msg)?)
+            serde_json::from_str::<to_client::ToClient>(msg).map_err(Error::malformed)
         },
         EncodingKind::Cbor => {
-            let msg = serde_cbor::from_slice::<to_client::ToClient>(
-                &BASE64_STANDARD.decode(msg.as_bytes())?
-            )?;
+            let bytes = BASE64_STANDARD.decode(msg.as_bytes()).map_err(Error::malformed)?;
 
-            Ok(msg)
+            serde_cbor::from_slice::<to_client::ToClient>(&bytes).map_err(Error::malformed)
         }
     }
 }
 
 fn serialize(encoding_kind: EncodingKind, msg: &to_server::ToServer) -> Result<Vec<u8>> {
     match encoding_kind {
-        EncodingKind::Json => Ok(serde_json::to_vec(msg)?),
-        EncodingKind::Cbor => Ok(serde_cbor::to_vec(msg)?),
+        EncodingKind::Json => serde_json::to_vec(msg).map_err(Error::serialization),
+        EncodingKind::Cbor => serde_cbor::to_vec(msg).map_err(Error::serialization),
     }
 }
 
@@ -1,65 +1,187 @@
-use anyhow::{Context, Result};
+use bytes::Bytes;
 use futures_util::{SinkExt, StreamExt};
 use std::sync::Arc;
 use tokio::net::TcpStream;
 use tokio::sync::mpsc;
-use tokio_tungstenite::tungstenite::Message;
+use tokio::time::{Instant, Interval, MissedTickBehavior};
+use tokio_tungstenite::tungstenite::{
+    client::IntoClientRequest,
+    error::ProtocolError,
+    handshake::client::Response,
+    http::{header::{SEC_WEBSOCKET_PROTOCOL, USER_AGENT}, HeaderName, HeaderValue},
+    Message,
+};
 use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use serde_json::Value as JsonValue;
 use tracing::debug;
 
 use crate::{
+    client::ClientConfig,
+    common::{ProtocolVersion, WS_PROTOCOL_CONN_PARAMS, WS_PROTOCOL_ENCODING, WS_PROTOCOL_QUERY, WS_PROTOCOL_STANDARD},
+    error::{Error, Result},
     protocol::to_server,
     protocol::to_client,
+    protocol::query::ActorQuery,
+    telemetry,
     EncodingKind
 };
 
 use super::{
-    DriverConnectArgs, DriverConnection, DriverHandle, DriverStopReason, MessageToClient, MessageToServer
+    sleep_until, DisconnectReason, DriverConnectArgs, DriverConnection, DriverHandle, DriverStopReason, DriverEvent, HeartbeatPolicy, MessageToServer
 };
 
-fn build_connection_url(args: &DriverConnectArgs) -> Result<String> {
-    let actor_query_string = serde_json::to_string(&args.query)?;
-    // TODO: Should replace http:// only at the start of the string
-    let url = args.endpoint
-        .to_string()
-        .replace("http://", "ws://")
-        .replace("https://", "wss://");
+/// Waits for the next ping, forever if pings are disabled.
+async fn tick(interval: Option<&mut Interval>) {
+    match interval {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// Swaps the endpoint's `http(s)://` scheme for `ws(s)://`.
+pub(crate) fn websocket_endpoint(endpoint: &str) -> String {
+    if let Some(rest) = endpoint.strip_prefix("http://") {
+        format!("ws://{}", rest)
+    } else if let Some(rest) = endpoint.strip_prefix("https://") {
+        format!("wss://{}", rest)
+    } else {
+        endpoint.to_string()
+    }
+}
+
+fn build_connection_url(args: &DriverConnectArgs, version: ProtocolVersion) -> Result<String> {
+    let url = format!("{}/actors/connect/websocket", websocket_endpoint(&args.config.endpoint));
+
+    if version != ProtocolVersion::Legacy {
+        return Ok(url);
+    }
 
-    let url = format!(
-        "{}/actors/connect/websocket?encoding={}&query={}",
+    let actor_query_string = serde_json::to_string(&args.query).map_err(Error::serialization)?;
+    Ok(format!(
+        "{}?encoding={}&query={}",
         url,
-        args.encoding_kind.as_str(),
+        args.config.encoding_kind.as_str(),
         urlencoding::encode(&actor_query_string)
-    );
+    ))
+}
+
+/// Connection parameters sent as `Sec-WebSocket-Protocol` entries.
+pub(crate) fn build_subprotocols(
+    query: &ActorQuery,
+    encoding_kind: EncodingKind,
+    parameters: Option<&JsonValue>,
+) -> Result<Vec<String>> {
+    let actor_query_string = serde_json::to_string(query).map_err(Error::serialization)?;
+
+    let mut protocols = vec![
+        WS_PROTOCOL_STANDARD.to_string(),
+        format!("{}{}", WS_PROTOCOL_QUERY, urlencoding::encode(&actor_query_string)),
+        format!("{}{}", WS_PROTOCOL_ENCODING, encoding_kind.as_str()),
+    ];
+
+    if let Some(params) = parameters {
+        let params = serde_json::to_string(params).map_err(Error::serialization)?;
+        protocols.push(format!("{}{}", WS_PROTOCOL_CONN_PARAMS, urlencoding::encode(&params)));
+    }
 
-    Ok(url)
+    Ok(protocols)
 }
 
+/// Whether a failed upgrade looks like a manager that only speaks the legacy
+/// dialect.
+fn is_legacy_rejection(err: &Error) -> bool {
+    let Error::Transport(err) = err else {
+        return false;
+    };
 
-pub(crate) async fn connect(args: DriverConnectArgs) -> Result<DriverConnection> {
-    let url = build_connection_url(&args)?;
+    match err.downcast_ref::<tungstenite::Error>() {
+        Some(tungstenite::Error::Protocol(ProtocolError::SecWebSocketSubProtocolError(_))) => true,
+        Some(tungstenite::Error::Http(res)) => matches!(res.status().as_u16(), 400 | 404),
+        _ => false,
+    }
+}
+
+/// Opens a WebSocket to `url` with the client's headers & connect timeout.
+pub(crate) async fn open_websocket(
+    config: &ClientConfig,
+    url: &str,
+    protocols: Option<&[String]>,
+    extra_headers: &[(String, String)],
+) -> Result<(WebSocketStream<MaybeTlsStream<TcpStream>>, Response)> {
+    let mut request = url.into_client_request()?;
+    let headers = request.headers_mut();
+    headers.insert(USER_AGENT, header_value(&config.user_agent)?);
+    if let Some(protocols) = protocols {
+        headers.insert(SEC_WEBSOCKET_PROTOCOL, header_value(&protocols.join(", "))?);
+    }
+    for (key, value) in config.headers.iter().chain(extra_headers) {
+        let key = HeaderName::from_bytes(key.as_bytes()).map_err(Error::serialization)?;
+        headers.insert(key, header_value(value)?);
+    }
+
+    let connect = tokio_tungstenite::connect_async(request);
+    let res = match config.connect_timeout {
+        Some(timeout) => tokio::time::timeout(timeout, connect)
+            .await
+            .map_err(|_| Error::Timeout)??,
+        None => connect.await?,
+    };
 
-    debug!("Connecting to: {}", url);
+    Ok(res)
+}
 
-    let (ws, _res) = tokio_tungstenite::connect_async(url)
-        .await
-        .context("Failed to connect to WebSocket")?;
+async fn open(
+    args: &DriverConnectArgs,
+    version: ProtocolVersion,
+) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>> {
+    let url = build_connection_url(args, version)?;
 
-    let (in_tx, in_rx) = mpsc::channel::<MessageToClient>(32);
-    let (out_tx, out_rx) = mpsc::channel::<MessageToServer>(32);
+    debug!("Connecting to: {} ({:?})", url, version);
 
-    let task = tokio::spawn(start(ws, args.encoding_kind, in_tx, out_rx));
+    let protocols = match version {
+        ProtocolVersion::Legacy => None,
+        _ => Some(build_subprotocols(&args.query, args.config.encoding_kind, args.parameters.as_ref())?),
+    };
+    let (ws, _res) = open_websocket(&args.config, &url, protocols.as_deref(), &args.headers).await?;
+
+    Ok(ws)
+}
+
+pub(crate) async fn connect(args: DriverConnectArgs) -> Result<DriverConnection> {
+    let (ws, version) = match args.config.effective_protocol() {
+        ProtocolVersion::Auto => match open(&args, ProtocolVersion::Current).await {
+            Ok(ws) => (ws, ProtocolVersion::Current),
+            Err(e) if is_legacy_rejection(&e) => {
+                debug!("Manager rejected current protocol, falling back to legacy: {}", e);
+                (open(&args, ProtocolVersion::Legacy).await?, ProtocolVersion::Legacy)
+            }
+            Err(e) => return Err(e),
+        },
+        version => (open(&args, version).await?, version),
+    };
+    args.config.set_detected_protocol(version);
+
+    let capacity = args.config.channel_capacity;
+    let (in_tx, in_rx) = mpsc::channel::<DriverEvent>(capacity);
+    let (out_tx, out_rx) = mpsc::channel::<MessageToServer>(capacity);
+
+    let task = tokio::spawn(start(ws, args.config.encoding_kind, args.config.heartbeat, in_tx, out_rx));
     let handle = DriverHandle::new(out_tx, task.abort_handle());
 
-    handle.send(Arc::new(
-        to_server::ToServer {
-            b: to_server::ToServerBody::Init {
-                i: to_server::Init {
-                    p: args.parameters
-                }
-            },
-        }
-    )).await?;
+    // Current protocol passes the parameters in the subprotocols
+    if version == ProtocolVersion::Legacy {
+        handle.send(Arc::new(
+            to_server::ToServer {
+                b: to_server::ToServerBody::Init {
+                    i: to_server::Init {
+                        p: args.parameters
+                    }
+                },
+            }
+        )).await?;
+    }
 
     Ok((handle, in_rx, task))
 }
@@ -67,22 +189,42 @@ pub(crate) async fn connect(args: DriverConnectArgs) -> Result<DriverConnection>
 async fn start(
     ws: WebSocketStream<MaybeTlsStream<TcpStream>>,
     encoding_kind: EncodingKind,
-    in_tx: mpsc::Sender<MessageToClient>,
+    heartbeat: HeartbeatPolicy,
+    in_tx: mpsc::Sender<DriverEvent>,
     mut out_rx: mpsc::Receiver<MessageToServer>,
-) -> DriverStopReason {
+) -> DisconnectReason {
     let (mut ws_sink, mut ws_stream) = ws.split();
 
     let serialize = get_msg_serializer(encoding_kind);
     let deserialize = get_msg_deserializer(encoding_kind);
 
+    let mut ping_interval = heartbeat.ping_interval.map(|period| {
+        let mut interval = tokio::time::interval_at(Instant::now() + period, period);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        interval
+    });
+    // Set while a ping is waiting for its pong
+    let mut pong_deadline = None;
+
     loop {
         tokio::select! {
+            _ = tick(ping_interval.as_mut()), if pong_deadline.is_none() => {
+                if let Err(e) = ws_sink.send(Message::Ping(Bytes::new())).await {
+                    debug!("Failed to send ping: {:?}", e);
+                    continue;
+                }
+                pong_deadline = Some(Instant::now() + heartbeat.pong_timeout);
+            },
+            _ = sleep_until(pong_deadline) => {
+                debug!("Pong not received in time");
+                return DriverStopReason::HeartbeatTimeout.into();
+            },
             // Dispatch ws outgoing queue
             msg = out_rx.recv() => {
                 // If the sender is dropped, break the loop
                 let Some(msg) = msg else {
                     debug!("Sender dropped");
-                    return DriverStopReason::UserAborted;
+                    return DriverStopReason::UserAborted.into();
                 };
 
                 let msg = match serialize(&msg) {
@@ -93,43 +235,58 @@ async fn start(
                     }
                 };
 
+                let len = msg.len();
                 if let Err(e) = ws_sink.send(msg).await {
                     debug!("Failed to send message: {:?}", e);
                     continue;
                 }
+                telemetry::message_sent(encoding_kind, len);
             },
             // Handle ws incoming
             msg = ws_stream.next() => {
                 let Some(msg) = msg else {
                     println!("Receiver dropped");
-                    return DriverStopReason::ServerDisconnect;
+                    return DriverStopReason::ServerDisconnect.into();
                 };
 
+                // Anything from the server proves the connection is alive
+                if msg.is_ok() {
+                    pong_deadline = None;
+                }
+
                 match msg {
                     Ok(msg) => match msg {
                         Message::Text(_) | Message::Binary(_) => {
+                            telemetry::message_received(encoding_kind, msg.len());
                             let Ok(msg) = deserialize(&msg) else {
                                 debug!("Failed to parse message: {:?}", msg);
+                                telemetry::deserialization_failed(encoding_kind);
                                 continue;
                             };
 
-                            if let Err(e) = in_tx.send(Arc::new(msg)).await {
+                            if let Err(e) = in_tx.send(DriverEvent::Message(Arc::new(msg))).await {
                                 debug!("Failed to send text message: {}", e);
                                 // failure to send means user dropped incoming receiver
-                                return DriverStopReason::UserAborted;
+                                return DriverStopReason::UserAborted.into();
                             }
                         },
-                        Message::Close(_) => {
-                            debug!("Close message");
-                            return DriverStopReason::ServerDisconnect;
+                        Message::Close(frame) => {
+                            debug!("Close message: {:?}", frame);
+                            return DisconnectReason {
+                                stop_reason: DriverStopReason::ServerDisconnect,
+                                close_code: frame.as_ref().map(|f| u16::from(f.code)),
+                                close_reason: frame.map(|f| f.reason.to_string()),
+                                http_status: None,
+                            };
                         },
+                        Message::Ping(_) | Message::Pong(_) => {}
                         _ => {
                             debug!("Invalid message type received");
                         }
                     }
                     Err(e) => {
                         debug!("WebSocket error: {}", e);
-                        return DriverStopReason::ServerError;
+                        return DriverStopReason::ServerError.into();
                     }
                 }
             }
@@ -137,6 +294,10 @@ async fn start(
     }
 }
 
+fn header_value(value: &str) -> Result<HeaderValue> {
+    HeaderValue::from_str(value).map_err(Error::serialization)
+}
+
 fn get_msg_deserializer(encoding_kind: EncodingKind) -> fn(&Message) -> Result<to_client::ToClient> {
     match encoding_kind {
         EncodingKind::Json => json_msg_deserialize,
@@ -153,24 +314,26 @@ fn get_msg_serializer(encoding_kind: EncodingKind) -> fn(&to_server::ToServer) -
 
 fn json_msg_deserialize(value: &Message) -> Result<to_client::ToClient> {
     match value {
-        Message::Text(text) => Ok(serde_json::from_str(text)?),
-        Message::Binary(bin) => Ok(serde_json::from_slice(bin)?),
-        _ => Err(anyhow::anyhow!("Invalid message type")),
+        Message::Text(text) => serde_json::from_str(text).map_err(Error::malformed),
+        Message::Binary(bin) => serde_json::from_slice(bin).map_err(Error::malformed),
+        _ => Err(Error::malformed("Invalid message type")),
     }
 }
 
 fn cbor_msg_deserialize(value: &Message) -> Result<to_client::ToClient> {
     match value {
-        Message::Binary(bin) => Ok(serde_cbor::from_slice(bin)?),
-        Message::Text(text) => Ok(serde_cbor::from_slice(text.as_bytes())?),
-        _ => Err(anyhow::anyhow!("Invalid message type")),
+        Message::Binary(bin) => serde_cbor::from_slice(bin).map_err(Error::malformed),
+        Message::Text(text) => serde_cbor::from_slice(text.as_bytes()).map_err(Error::malformed),
+        _ => Err(Error::malformed("Invalid message type")),
     }
 }
 
 fn json_msg_serialize(value: &to_server::ToServer) -> Result<Message> {
-    Ok(Message::Text(serde_json::to_string(value)?.into()))
+    let text = serde_json::to_string(value).map_err(Error::serialization)?;
+    Ok(Message::Text(text.into()))
 }
 
 fn cbor_msg_serialize(value: &to_server::ToServer) -> Result<Message> {
-    Ok(Message::Binary(serde_cbor::to_vec(value)?.into()))
+    let bin = serde_cbor::to_vec(value).map_err(Error::serialization)?;
+    Ok(Message::Binary(bin.into()))
 }
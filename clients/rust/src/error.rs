@@ -0,0 +1,257 @@
+use serde::de::DeserializeOwned;
+use serde_json::Value as JsonValue;
+
+use crate::protocol::{http, to_client};
+
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Errors returned by the RivetKit client.
+///
+/// Mirrors the error classes in the TypeScript client (`client/errors.ts`).
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Error raised by the actor (or by the manager on behalf of the actor),
+    /// e.g. `unauthorized`, `not_found` or a user error.
+    #[error("actor error ({code}): {message}")]
+    Actor {
+        code: String,
+        message: String,
+        metadata: Option<JsonValue>,
+    },
+
+    /// The manager failed to resolve or create an actor without a structured
+    /// error, e.g. an unexpected status or response body.
+    #[error("manager error: {0}")]
+    Manager(String),
+
+    /// The actor query cannot be used for this operation, e.g. resolving a
+    /// create query.
+    #[error("invalid actor query: {0}")]
+    InvalidQuery(String),
+
+    /// The client hit an unexpected internal state.
+    #[error("internal client error: {0}")]
+    Internal(String),
+
+    /// Non-2xx response whose body could not be decoded as a structured error.
+    #[error("http request failed with status {status}: {body}")]
+    HttpStatus { status: u16, body: String },
+
+    /// The server responded with a body that could not be decoded.
+    #[error("malformed response: {0}")]
+    MalformedResponse(#[source] BoxError),
+
+    /// A response or event payload could not be converted to the requested
+    /// type.
+    #[error("failed to deserialize payload: {0}")]
+    Deserialization(#[source] BoxError),
+
+    /// The client was built with invalid settings.
+    #[error("invalid client configuration: {0}")]
+    Config(String),
+
+    /// The request could not be encoded before being sent.
+    #[error("failed to serialize request: {0}")]
+    Serialization(#[source] BoxError),
+
+    /// The connection stopped reconnecting, see
+    /// [`ConnectionError::RetriesExhausted`].
+    #[error("gave up reconnecting after {attempts} attempts")]
+    RetriesExhausted { attempts: u32 },
+
+    /// The connection dropped after the action was sent, it may or may not
+    /// have run. See [`InFlightPolicy`](crate::InFlightPolicy).
+    #[error("connection lost while the action was in flight")]
+    ConnectionLost,
+
+    /// The actor connection was disconnected while the operation was pending.
+    #[error("attempting to interact with a disposed actor connection")]
+    ConnectionDisposed,
+
+    /// The underlying HTTP, WebSocket or SSE transport failed.
+    #[error("transport error: {0}")]
+    Transport(#[source] BoxError),
+
+    /// The operation did not complete in time.
+    #[error("operation timed out")]
+    Timeout,
+
+    /// The operation was cancelled through its cancellation token.
+    #[error("operation cancelled")]
+    Cancelled,
+
+    /// The [`CredentialProvider`](crate::CredentialProvider) failed.
+    #[error("failed to get credentials: {0}")]
+    Auth(String),
+
+    /// The connection is down and offline queueing is disabled.
+    #[error("actor connection is not connected")]
+    NotConnected,
+
+    /// The message was dropped because the offline queue is full.
+    #[error("offline message queue is full")]
+    QueueFull,
+
+    /// The message stayed in the offline queue for longer than its max age.
+    #[error("message expired in the offline queue")]
+    QueueExpired,
+
+    /// An event listener panicked, reported through
+    /// [`on_error`](crate::connection::ActorConnectionInner::on_error).
+    #[error("listener for event {event} panicked: {message}")]
+    EventCallbackPanicked { event: String, message: String },
+}
+
+impl Error {
+    pub(crate) fn transport(err: impl Into<BoxError>) -> Self {
+        Error::Transport(err.into())
+    }
+
+    pub(crate) fn malformed(err: impl Into<BoxError>) -> Self {
+        Error::MalformedResponse(err.into())
+    }
+
+    pub(crate) fn serialization(err: impl Into<BoxError>) -> Self {
+        Error::Serialization(err.into())
+    }
+
+    pub(crate) fn deserialization(err: impl Into<BoxError>) -> Self {
+        Error::Deserialization(err.into())
+    }
+
+    /// Error code if this is an actor error.
+    pub fn code(&self) -> Option<&str> {
+        match self {
+            Error::Actor { code, .. } => Some(code),
+            _ => None,
+        }
+    }
+
+    /// Decodes the metadata attached to an actor error.
+    ///
+    /// Returns `Ok(None)` if this is not an actor error or the error has no
+    /// metadata.
+    pub fn metadata<T: DeserializeOwned>(&self) -> Result<Option<T>, serde_json::Error> {
+        match self {
+            Error::Actor { metadata: Some(md), .. } => serde_json::from_value(md.clone()).map(Some),
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Connection-level error, e.g. a rejected handshake.
+///
+/// Unlike [`Error`] it can be cloned, so a fatal error can be handed to every
+/// pending action and to [`ConnectionStatus::Failed`](crate::ConnectionStatus::Failed).
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ConnectionError {
+    /// Error sent by the actor or manager over the connection.
+    #[error("actor error ({code}): {message}")]
+    Actor {
+        code: String,
+        message: String,
+        metadata: Option<JsonValue>,
+    },
+
+    /// The WebSocket or SSE upgrade was rejected.
+    #[error("connection rejected with status {status}")]
+    HttpStatus { status: u16, body: String },
+
+    /// The client was built with settings that cannot connect, e.g. an
+    /// invalid endpoint.
+    #[error("invalid client configuration: {0}")]
+    Config(String),
+
+    /// The [`CredentialProvider`](crate::CredentialProvider) failed.
+    #[error("failed to get credentials: {0}")]
+    Auth(String),
+
+    /// The [`ReconnectPolicy`](crate::ReconnectPolicy) gave up after
+    /// `attempts` failed reconnect attempts.
+    #[error("gave up reconnecting after {attempts} attempts")]
+    RetriesExhausted { attempts: u32 },
+}
+
+impl ConnectionError {
+    /// Error code if this is an actor error.
+    pub fn code(&self) -> Option<&str> {
+        match self {
+            ConnectionError::Actor { code, .. } => Some(code),
+            _ => None,
+        }
+    }
+}
+
+/// Error yielded by an [`EventStream`](crate::EventStream).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum EventStreamError {
+    /// The stream fell behind and this many events were discarded.
+    #[error("event stream lagged behind by {0} events")]
+    Lagged(u64),
+}
+
+impl From<ConnectionError> for Error {
+    fn from(err: ConnectionError) -> Self {
+        match err {
+            ConnectionError::Actor { code, message, metadata } => Error::Actor { code, message, metadata },
+            ConnectionError::HttpStatus { status, body } => Error::HttpStatus { status, body },
+            ConnectionError::Config(message) => Error::Config(message),
+            ConnectionError::Auth(message) => Error::Auth(message),
+            ConnectionError::RetriesExhausted { attempts } => Error::RetriesExhausted { attempts },
+        }
+    }
+}
+
+impl From<to_client::Error> for ConnectionError {
+    fn from(err: to_client::Error) -> Self {
+        ConnectionError::Actor {
+            code: err.c,
+            message: err.m,
+            metadata: err.md,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_timeout() {
+            Error::Timeout
+        } else {
+            Error::transport(err)
+        }
+    }
+}
+
+impl From<tungstenite::Error> for Error {
+    fn from(err: tungstenite::Error) -> Self {
+        Error::transport(err)
+    }
+}
+
+impl From<eventsource_client::Error> for Error {
+    fn from(err: eventsource_client::Error) -> Self {
+        Error::transport(err)
+    }
+}
+
+impl From<to_client::Error> for Error {
+    fn from(err: to_client::Error) -> Self {
+        Error::Actor {
+            code: err.c,
+            message: err.m,
+            metadata: err.md,
+        }
+    }
+}
+
+impl From<http::ResponseError> for Error {
+    fn from(err: http::ResponseError) -> Self {
+        Error::Actor {
+            code: err.c,
+            message: err.m,
+            metadata: err.md,
+        }
+    }
+}
@@ -0,0 +1,234 @@
+use std::{
+    collections::VecDeque,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use futures_util::{stream::BoxStream, Stream, StreamExt};
+use serde_json::Value;
+use tokio::sync::Notify;
+
+use crate::{connection::Subscription, error::EventStreamError};
+
+/// Event broadcast by the actor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub name: String,
+    pub args: Vec<Value>,
+}
+
+/// What an [`EventStream`] does with an event once its buffer is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowPolicy {
+    /// Waits for the stream to catch up. This stalls delivery of all events
+    /// on the connection, but not action responses, until there is room.
+    Block,
+    /// Discards the oldest buffered event.
+    DropOldest,
+    /// Discards the incoming event.
+    DropNewest,
+    /// Discards the oldest buffered event and yields
+    /// [`EventStreamError::Lagged`] with the number of discarded events, like
+    /// `tokio::sync::broadcast`.
+    #[default]
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventStreamOptions {
+    /// Number of events buffered before `overflow` applies.
+    pub buffer: usize,
+    pub overflow: OverflowPolicy,
+}
+
+impl Default for EventStreamOptions {
+    fn default() -> Self {
+        Self {
+            buffer: 64,
+            overflow: OverflowPolicy::default(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct BufferState {
+    events: VecDeque<Event>,
+    lagged: u64,
+    sender_closed: bool,
+    receiver_closed: bool,
+}
+
+/// Queue shared by an [`EventSender`] and its [`EventStream`].
+struct EventBuffer {
+    state: Mutex<BufferState>,
+    options: EventStreamOptions,
+    /// Woken when an event is pushed or the sender is closed.
+    readable: Notify,
+    /// Woken when an event is taken or the stream is dropped.
+    writable: Notify,
+}
+
+impl EventBuffer {
+    fn new(options: EventStreamOptions) -> Self {
+        Self {
+            state: Mutex::new(BufferState::default()),
+            options: EventStreamOptions {
+                buffer: options.buffer.max(1),
+                ..options
+            },
+            readable: Notify::new(),
+            writable: Notify::new(),
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, BufferState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    async fn recv(&self) -> Option<Result<Event, EventStreamError>> {
+        loop {
+            {
+                let mut state = self.state();
+                if state.lagged > 0 {
+                    let lagged = std::mem::take(&mut state.lagged);
+                    return Some(Err(EventStreamError::Lagged(lagged)));
+                }
+                if let Some(event) = state.events.pop_front() {
+                    self.writable.notify_one();
+                    return Some(Ok(event));
+                }
+                if state.sender_closed {
+                    return None;
+                }
+            }
+
+            self.readable.notified().await;
+        }
+    }
+
+    fn close_receiver(&self) {
+        let mut state = self.state();
+        state.receiver_closed = true;
+        state.events.clear();
+        self.writable.notify_one();
+    }
+}
+
+/// Delivers events into an [`EventStream`], ending it when dropped.
+pub(crate) struct EventSender {
+    buffer: Arc<EventBuffer>,
+}
+
+impl EventSender {
+    pub(crate) fn is_closed(&self) -> bool {
+        self.buffer.state().receiver_closed
+    }
+
+    pub(crate) async fn send(&self, event: Event) {
+        let buffer = &self.buffer;
+
+        loop {
+            {
+                let mut state = buffer.state();
+                if state.receiver_closed {
+                    return;
+                }
+
+                if state.events.len() < buffer.options.buffer {
+                    state.events.push_back(event);
+                    buffer.readable.notify_one();
+                    return;
+                }
+
+                match buffer.options.overflow {
+                    OverflowPolicy::Block => {}
+                    OverflowPolicy::DropOldest => {
+                        state.events.pop_front();
+                        state.events.push_back(event);
+                        return;
+                    }
+                    OverflowPolicy::DropNewest => return,
+                    OverflowPolicy::Error => {
+                        state.events.pop_front();
+                        state.events.push_back(event);
+                        state.lagged += 1;
+                        buffer.readable.notify_one();
+                        return;
+                    }
+                }
+            }
+
+            buffer.writable.notified().await;
+        }
+    }
+}
+
+impl Drop for EventSender {
+    fn drop(&mut self) {
+        self.buffer.state().sender_closed = true;
+        self.buffer.readable.notify_one();
+    }
+}
+
+/// Stream of events returned by
+/// [`ActorConnectionInner::events`](crate::connection::ActorConnectionInner::events)
+/// and [`ActorConnectionInner::all_events`](crate::connection::ActorConnectionInner::all_events).
+///
+/// Yields [`EventStreamError::Lagged`] only with [`OverflowPolicy::Error`].
+/// The stream ends once the connection is disposed or failed, dropping it
+/// removes the listener.
+pub struct EventStream {
+    buffer: Arc<EventBuffer>,
+    inner: BoxStream<'static, Result<Event, EventStreamError>>,
+    /// Server subscription for streams of a single event.
+    subscription: Option<Subscription>,
+}
+
+impl EventStream {
+    pub(crate) fn channel(options: EventStreamOptions) -> (EventSender, Self) {
+        let buffer = Arc::new(EventBuffer::new(options));
+
+        let inner = futures_util::stream::unfold(buffer.clone(), |buffer| async move {
+            let item = buffer.recv().await?;
+            Some((item, buffer))
+        })
+        .boxed();
+
+        (
+            EventSender { buffer: buffer.clone() },
+            Self {
+                buffer,
+                inner,
+                subscription: None,
+            },
+        )
+    }
+
+    pub(crate) fn set_subscription(&mut self, subscription: Subscription) {
+        self.subscription = Some(subscription);
+    }
+}
+
+impl Stream for EventStream {
+    type Item = Result<Event, EventStreamError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.poll_next_unpin(cx)
+    }
+}
+
+impl Drop for EventStream {
+    fn drop(&mut self) {
+        self.buffer.close_receiver();
+    }
+}
+
+impl std::fmt::Debug for EventStream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventStream")
+            .field("options", &self.buffer.options)
+            .field("subscription", &self.subscription)
+            .finish()
+    }
+}
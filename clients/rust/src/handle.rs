@@ -1,38 +1,47 @@
-use std::{cell::RefCell, ops::Deref, sync::Arc};
+use std::{cell::RefCell, ops::Deref, sync::Arc, time::Instant};
+use serde::{de::DeserializeOwned, Serialize};
 use serde_json::Value as JsonValue;
-use anyhow::{anyhow, Result};
 use urlencoding::encode as url_encode;
 use crate::{
-    common::{resolve_actor_id, send_http_request, HttpRequestOptions, HEADER_ACTOR_QUERY, HEADER_CONN_PARAMS, HEADER_ENCODING},
+    client::ClientConfig,
+    common::{deserialize_value, ActionOptions, resolve_actor_id, send_http_request, serialize_args, HttpRequestOptions, ProtocolHeader},
     connection::{start_connection, ActorConnection, ActorConnectionInner},
+    credentials::{credentials, RequestKind},
+    error::{Error, Result},
     protocol::query::*,
-    EncodingKind,
-    TransportKind
+    raw::{self, RawRequest, RawResponse, RawWebSocket},
+    telemetry,
 };
 
 pub struct ActorHandleStateless {
-    endpoint: String,
+    config: Arc<ClientConfig>,
     params: Option<JsonValue>,
-    encoding_kind: EncodingKind,
     query: RefCell<ActorQuery>,
 }
 
 impl ActorHandleStateless {
     pub fn new(
-        endpoint: &str,
+        config: Arc<ClientConfig>,
         params: Option<JsonValue>,
-        encoding_kind: EncodingKind,
         query: ActorQuery
     ) -> Self {
         Self {
-            endpoint: endpoint.to_string(),
+            config,
             params,
-            encoding_kind,
             query: RefCell::new(query)
         }
     }
 
     pub async fn action(&self, name: &str, args: Vec<JsonValue>) -> Result<JsonValue> {
+        self.action_with_options(name, args, ActionOptions::default()).await
+    }
+
+    pub async fn action_with_options(
+        &self,
+        name: &str,
+        args: Vec<JsonValue>,
+        opts: ActionOptions
+    ) -> Result<JsonValue> {
         #[derive(serde::Serialize)]
         struct ActionRequest {
             a: Vec<JsonValue>,
@@ -42,22 +51,28 @@ impl ActorHandleStateless {
             o: JsonValue,
         }
 
-        let actor_query = serde_json::to_string(&self.query)?;
+        let query = self.query.borrow().clone();
+        let actor_query = serde_json::to_string(&query).map_err(Error::serialization)?;
+        let credentials = credentials(&self.config, RequestKind::Action, &query, self.params.as_ref()).await?;
 
         // Build headers
-        let mut headers = vec![
-            (HEADER_ENCODING, self.encoding_kind.to_string()),
-            (HEADER_ACTOR_QUERY, actor_query),
-        ];
+        let mut headers = Vec::new();
+        headers.extend(self.config.protocol_headers(ProtocolHeader::Encoding, self.config.encoding_kind.as_str()));
+        headers.extend(self.config.protocol_headers(ProtocolHeader::ActorQuery, &actor_query));
 
-        if let Some(params) = &self.params {
-            headers.push((HEADER_CONN_PARAMS, serde_json::to_string(params)?));
+        if let Some(params) = &credentials.params {
+            let params = serde_json::to_string(params).map_err(Error::serialization)?;
+            headers.extend(self.config.protocol_headers(ProtocolHeader::ConnParams, &params));
         }
 
-        let res = send_http_request::<ActionRequest, ActionResponse>(HttpRequestOptions {
+        headers.extend(credentials.header_pairs());
+        headers.extend(opts.header_pairs());
+
+        let started = Instant::now();
+        let res = opts.run(send_http_request::<ActionRequest, ActionResponse>(&self.config, HttpRequestOptions {
             url: &format!(
                 "{}/actors/actions/{}",
-                self.endpoint,
+                self.config.endpoint,
                 url_encode(name)
             ),
             method: "POST",
@@ -65,19 +80,60 @@ impl ActorHandleStateless {
             body: Some(ActionRequest {
                 a: args,
             }),
-            encoding_kind: self.encoding_kind,
-        }).await?;
+            encoding_kind: self.config.encoding_kind,
+        })).await;
+        telemetry::action_completed(name, telemetry::TRANSPORT_HTTP, started.elapsed());
+
+        Ok(res?.o)
+    }
 
-        Ok(res.o)
+    /// Typed variant of [`ActorHandleStateless::action`].
+    ///
+    /// `args` is serialized as the positional argument array, so pass a tuple
+    /// (e.g. `("william", 1)`, or `()` for no arguments).
+    pub async fn action_typed<A, R>(&self, name: &str, args: A) -> Result<R>
+    where
+        A: Serialize,
+        R: DeserializeOwned,
+    {
+        let output = self.action(name, serialize_args(args)?).await?;
+
+        deserialize_value(output)
+    }
+
+    /// Sends `request` to the actor's `onFetch` handler at `path`.
+    ///
+    /// The response is returned as-is, including non-2xx statuses.
+    pub async fn fetch(&self, path: &str, request: RawRequest) -> Result<RawResponse> {
+        let query = self.query.borrow().clone();
+        let actor_query = serde_json::to_string(&query).map_err(Error::serialization)?;
+        let credentials = credentials(&self.config, RequestKind::Raw, &query, self.params.as_ref()).await?;
+
+        raw::fetch(&self.config, &actor_query, &credentials, path, request).await
+    }
+
+    /// Opens a WebSocket to the actor's `onWebSocket` handler at `path`.
+    ///
+    /// `subprotocols` are offered to the actor alongside the ones used to
+    /// pass the actor query.
+    pub async fn raw_websocket(&self, path: &str, subprotocols: &[&str]) -> Result<RawWebSocket> {
+        let query = self.query.borrow().clone();
+        let credentials = credentials(&self.config, RequestKind::Raw, &query, self.params.as_ref()).await?;
+
+        raw::websocket(&self.config, &query, &credentials, path, subprotocols).await
     }
 
     pub async fn resolve(&self) -> Result<String> {
+        self.resolve_with_options(ActionOptions::default()).await
+    }
+
+    pub async fn resolve_with_options(&self, opts: ActionOptions) -> Result<String> {
         let query = {
             // None of this is async or runs on multithreads,
             // it cannot fail given that both borrows are
             // well contained, and cannot overlap.
             let Ok(query) = self.query.try_borrow() else {
-                return Err(anyhow!("Failed to borrow actor query"));
+                return Err(Error::Internal("failed to borrow actor query".to_string()));
             };
 
             query.clone()
@@ -85,22 +141,22 @@ impl ActorHandleStateless {
 
         match query {
             ActorQuery::Create { create: _query } => {
-                Err(anyhow!("actor query cannot be create"))
+                Err(Error::InvalidQuery("create queries cannot be resolved".to_string()))
             },
             ActorQuery::GetForId { get_for_id: query } => {
                 Ok(query.clone().actor_id)
             },
             _ => {
                 let actor_id = resolve_actor_id(
-                    &self.endpoint,
+                    &self.config,
                     query,
-                    self.encoding_kind
+                    &opts,
                 ).await?;
 
                 {
                     let Ok(mut query) = self.query.try_borrow_mut() else {
                         // Following code will not run (see prior note)
-                        return Err(anyhow!("Failed to borrow actor query mutably"));
+                        return Err(Error::Internal("failed to borrow actor query mutably".to_string()));
                     };
 
                     *query = ActorQuery::GetForId {
@@ -118,47 +174,38 @@ impl ActorHandleStateless {
 
 pub struct ActorHandle {
     handle: ActorHandleStateless,
-    endpoint: String,
+    config: Arc<ClientConfig>,
     params: Option<JsonValue>,
     query: ActorQuery,
     client_shutdown_tx: Arc<tokio::sync::broadcast::Sender<()>>,
-    transport_kind: crate::TransportKind,
-    encoding_kind: EncodingKind,
 }
 
 impl ActorHandle {
     pub fn new(
-        endpoint: &str,
+        config: Arc<ClientConfig>,
         params: Option<JsonValue>,
         query: ActorQuery,
         client_shutdown_tx: Arc<tokio::sync::broadcast::Sender<()>>,
-        transport_kind: TransportKind,
-        encoding_kind: EncodingKind
     ) -> Self {
         let handle = ActorHandleStateless::new(
-            endpoint,
+            config.clone(),
             params.clone(),
-            encoding_kind,
             query.clone()
         );
 
         Self {
             handle,
-            endpoint: endpoint.to_string(),
+            config,
             params,
             query,
             client_shutdown_tx,
-            transport_kind,
-            encoding_kind,
         }
     }
 
     pub fn connect(&self) -> ActorConnection {
         let conn = ActorConnectionInner::new(
-            self.endpoint.clone(),
+            self.config.clone(),
             self.query.clone(),
-            self.transport_kind,
-            self.encoding_kind,
             self.params.clone()
         );
 
@@ -167,6 +214,20 @@ impl ActorHandle {
 
         conn
     }
+
+    /// Connects and waits for the handshake, see
+    /// [`ActorConnectionInner::ready`].
+    ///
+    /// The connection is closed if the handshake fails.
+    pub async fn connect_and_wait(&self) -> Result<ActorConnection> {
+        let conn = self.connect();
+        if let Err(err) = conn.ready().await {
+            conn.disconnect().await;
+            return Err(err);
+        }
+
+        Ok(conn)
+    }
 }
 
 impl Deref for ActorHandle {
@@ -1,10 +1,27 @@
 mod backoff;
 mod common;
+mod credentials;
+mod events;
+mod msg_queue;
+mod telemetry;
+pub mod error;
 pub mod client;
 pub mod drivers;
 pub mod connection;
 pub mod handle;
 pub mod protocol;
+pub mod raw;
 
-pub use client::{Client, CreateOptions, GetOptions, GetOrCreateOptions, GetWithIdOptions};
-pub use common::{TransportKind, EncodingKind};
+pub use backoff::{Jitter, ReconnectPolicy, RetryContext, RetryDecision, RetryHook};
+pub use client::{Client, ClientBuilder, ClientConfig, CreateOptions, GetOptions, GetOrCreateOptions, GetWithIdOptions};
+pub use connection::{ConnectionInfo, ConnectionStatus, InFlightPolicy, Subscription};
+pub use credentials::{CredentialProvider, RequestContext, RequestKind};
+pub use msg_queue::{OfflineQueuePolicy, QueueOverflow};
+pub use events::{Event, EventStream, EventStreamOptions, OverflowPolicy};
+pub use drivers::{DisconnectReason, DriverStopReason, HeartbeatPolicy};
+pub use common::{ActionOptions, ActorKey, ProtocolVersion, TransportKind, EncodingKind};
+pub use raw::{RawMessage, RawRequest, RawResponse, RawWebSocket};
+pub use reqwest::Method;
+pub use tokio_util::sync::CancellationToken;
+pub use error::{ConnectionError, Error, EventStreamError, Result};
+pub use rivetkit_client_macros::actor_client;
@@ -0,0 +1,166 @@
+use std::{collections::VecDeque, sync::Arc, time::Duration};
+
+use tokio::time::Instant;
+
+use crate::{error::Error, protocol::to_server, telemetry};
+
+/// What happens to a message sent while the offline queue is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QueueOverflow {
+    /// Fails the new message with [`Error::QueueFull`].
+    #[default]
+    Reject,
+    /// Fails the oldest queued message with [`Error::QueueFull`] to make
+    /// room.
+    DropOldest,
+    /// Fails every queued message and the new one with
+    /// [`Error::QueueFull`].
+    FailFast,
+}
+
+/// Messages sent while an actor connection is down are queued and sent once
+/// it reconnects.
+///
+/// Dropped messages fail their action. Actions whose caller stopped waiting,
+/// e.g. after a timeout, are never sent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OfflineQueuePolicy {
+    /// When disabled actions fail with [`Error::NotConnected`] while the
+    /// connection is down.
+    pub enabled: bool,
+    pub max_len: usize,
+    /// Messages queued for longer fail with [`Error::QueueExpired`]. Checked
+    /// whenever a message is queued and on reconnect.
+    pub max_age: Option<Duration>,
+    pub overflow: QueueOverflow,
+}
+
+impl OfflineQueuePolicy {
+    pub fn disabled() -> Self {
+        Self {
+            enabled: false,
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for OfflineQueuePolicy {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_len: 1024,
+            max_age: None,
+            overflow: QueueOverflow::default(),
+        }
+    }
+}
+
+/// Message that will not be sent, with the error to fail its action with.
+pub(crate) type DroppedMsg = (Arc<to_server::ToServer>, Error);
+
+pub(crate) struct MsgQueue {
+    policy: OfflineQueuePolicy,
+    msgs: VecDeque<(Instant, Arc<to_server::ToServer>)>,
+}
+
+impl MsgQueue {
+    pub fn new(policy: OfflineQueuePolicy) -> Self {
+        Self {
+            policy,
+            msgs: VecDeque::new(),
+        }
+    }
+
+    /// Queues `msg`, returning the messages that were dropped instead.
+    pub fn push(&mut self, msg: Arc<to_server::ToServer>) -> Vec<DroppedMsg> {
+        let len = self.msgs.len();
+        let dropped = self.push_inner(msg);
+        self.record_depth(len);
+
+        dropped
+    }
+
+    fn push_inner(&mut self, msg: Arc<to_server::ToServer>) -> Vec<DroppedMsg> {
+        if !self.policy.enabled {
+            return vec![(msg, Error::NotConnected)];
+        }
+
+        let mut dropped = self.expire();
+
+        if self.msgs.len() >= self.policy.max_len {
+            match self.policy.overflow {
+                QueueOverflow::Reject => {
+                    dropped.push((msg, Error::QueueFull));
+                    return dropped;
+                }
+                QueueOverflow::DropOldest => {
+                    // Also covers `max_len == 0`
+                    match self.msgs.pop_front() {
+                        Some((_, oldest)) => dropped.push((oldest, Error::QueueFull)),
+                        None => {
+                            dropped.push((msg, Error::QueueFull));
+                            return dropped;
+                        }
+                    }
+                }
+                QueueOverflow::FailFast => {
+                    dropped.extend(self.msgs.drain(..).map(|(_, m)| (m, Error::QueueFull)));
+                    dropped.push((msg, Error::QueueFull));
+                    return dropped;
+                }
+            }
+        }
+
+        self.msgs.push_back((Instant::now(), msg));
+
+        dropped
+    }
+
+    /// Takes every queued message, split into the ones to send and the
+    /// expired ones.
+    pub fn drain(&mut self) -> (Vec<Arc<to_server::ToServer>>, Vec<DroppedMsg>) {
+        let len = self.msgs.len();
+        let expired = self.expire();
+        let msgs = self.msgs.drain(..).map(|(_, m)| m).collect();
+        self.record_depth(len);
+
+        (msgs, expired)
+    }
+
+    pub fn clear(&mut self) {
+        let len = self.msgs.len();
+        self.msgs.clear();
+        self.record_depth(len);
+    }
+
+    /// Reports the change in queue depth since it was `before`.
+    fn record_depth(&self, before: usize) {
+        let delta = self.msgs.len() as isize - before as isize;
+        if delta != 0 {
+            telemetry::queue_depth_changed(delta);
+        }
+    }
+
+    fn expire(&mut self) -> Vec<DroppedMsg> {
+        let Some(max_age) = self.policy.max_age else {
+            return Vec::new();
+        };
+
+        let mut expired = Vec::new();
+        while let Some((queued_at, _)) = self.msgs.front() {
+            if queued_at.elapsed() < max_age {
+                break;
+            }
+            let (_, msg) = self.msgs.pop_front().unwrap();
+            expired.push((msg, Error::QueueExpired));
+        }
+
+        expired
+    }
+}
+
+impl Drop for MsgQueue {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
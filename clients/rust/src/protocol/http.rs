@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+// Body of non-2xx responses from the manager & actor HTTP endpoints
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponseError {
+    // Code
+    pub c: String,
+    // Message
+    pub m: String,
+    // Metadata
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub md: Option<JsonValue>,
+}
@@ -1,3 +1,4 @@
 pub mod to_server;
 pub mod to_client;
-pub mod query;
\ No newline at end of file
+pub mod query;
+pub mod http;
\ No newline at end of file
@@ -0,0 +1,225 @@
+use std::{
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use bytes::Bytes;
+use futures_util::{Sink, Stream};
+use reqwest::{header::USER_AGENT, Body, Method};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use tracing::debug;
+use tungstenite::{http::header::SEC_WEBSOCKET_PROTOCOL, Message};
+
+use crate::{
+    client::ClientConfig,
+    common::{ActionOptions, EncodingKind, ProtocolHeader},
+    credentials::Credentials,
+    drivers::ws::{build_subprotocols, open_websocket, websocket_endpoint},
+    error::{Error, Result},
+    protocol::query::ActorQuery,
+};
+
+/// Response of [`ActorHandleStateless::fetch`](crate::handle::ActorHandleStateless::fetch).
+///
+/// The body is not read up front, use `bytes_stream()` to stream it.
+pub type RawResponse = reqwest::Response;
+
+/// Request forwarded to the actor's `onFetch` handler.
+#[derive(Debug, Default)]
+pub struct RawRequest {
+    pub method: Method,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<Body>,
+    /// Timeout & cancellation apply until the response head is received,
+    /// not to streaming the body.
+    pub options: ActionOptions,
+}
+
+impl RawRequest {
+    pub fn new(method: Method) -> Self {
+        Self {
+            method,
+            ..Default::default()
+        }
+    }
+
+    pub fn header(mut self, key: &str, value: &str) -> Self {
+        self.headers.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn body(mut self, body: impl Into<Body>) -> Self {
+        self.body = Some(body.into());
+        self
+    }
+
+    /// Streams the request body from `stream`.
+    pub fn body_stream<S>(mut self, stream: S) -> Self
+    where
+        S: futures_util::TryStream + Send + 'static,
+        S::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+        bytes::Bytes: From<S::Ok>,
+    {
+        self.body = Some(Body::wrap_stream(stream));
+        self
+    }
+
+    pub fn options(mut self, options: ActionOptions) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+/// Strips the leading slash so `path` can be appended to a raw endpoint.
+fn normalize_path(path: &str) -> &str {
+    path.strip_prefix('/').unwrap_or(path)
+}
+
+pub(crate) async fn fetch(
+    config: &ClientConfig,
+    actor_query: &str,
+    credentials: &Credentials,
+    path: &str,
+    request: RawRequest,
+) -> Result<RawResponse> {
+    let url = format!("{}/actors/raw/http/{}", config.endpoint, normalize_path(path));
+
+    debug!("Raw fetch: {} {}", request.method, url);
+
+    let mut req = config.http_client
+        .request(request.method, url)
+        .header(USER_AGENT, &config.user_agent);
+
+    for (key, value) in &config.headers {
+        req = req.header(key, value);
+    }
+
+    // Raw requests are always JSON so the actor can read the parameters
+    let mut protocol_headers = Vec::new();
+    protocol_headers.extend(config.protocol_headers(ProtocolHeader::Encoding, EncodingKind::Json.as_str()));
+    protocol_headers.extend(config.protocol_headers(ProtocolHeader::ActorQuery, actor_query));
+    if let Some(params) = &credentials.params {
+        let params = serde_json::to_string(params).map_err(Error::serialization)?;
+        protocol_headers.extend(config.protocol_headers(ProtocolHeader::ConnParams, &params));
+    }
+    protocol_headers.extend(credentials.header_pairs());
+    for (key, value) in protocol_headers {
+        req = req.header(key, value);
+    }
+
+    for (key, value) in request.options.header_pairs().chain(
+        request.headers.iter().map(|(k, v)| (k.as_str(), v.clone()))
+    ) {
+        req = req.header(key, value);
+    }
+
+    if let Some(body) = request.body {
+        req = req.body(body);
+    }
+
+    let res = request.options.run(async { Ok(req.send().await?) }).await?;
+
+    Ok(res)
+}
+
+/// Frame sent or received over a [`RawWebSocket`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RawMessage {
+    Text(String),
+    Binary(Bytes),
+}
+
+/// WebSocket tunnelled to the actor's `onWebSocket` handler.
+///
+/// Pings & pongs are answered automatically, the stream ends once the socket
+/// is closed.
+pub struct RawWebSocket {
+    ws: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    protocol: Option<String>,
+}
+
+impl RawWebSocket {
+    /// Subprotocol selected by the actor, if any.
+    pub fn protocol(&self) -> Option<&str> {
+        self.protocol.as_deref()
+    }
+}
+
+impl Stream for RawWebSocket {
+    type Item = Result<RawMessage>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            let msg = match ready!(Pin::new(&mut self.ws).poll_next(cx)) {
+                Some(Ok(msg)) => msg,
+                Some(Err(e)) => return Poll::Ready(Some(Err(e.into()))),
+                None => return Poll::Ready(None),
+            };
+
+            match msg {
+                Message::Text(text) => return Poll::Ready(Some(Ok(RawMessage::Text(text.to_string())))),
+                Message::Binary(data) => return Poll::Ready(Some(Ok(RawMessage::Binary(data)))),
+                Message::Close(_) => return Poll::Ready(None),
+                Message::Ping(_) | Message::Pong(_) | Message::Frame(_) => continue,
+            }
+        }
+    }
+}
+
+impl Sink<RawMessage> for RawWebSocket {
+    type Error = Error;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Pin::new(&mut self.ws).poll_ready(cx).map_err(Error::from)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: RawMessage) -> Result<()> {
+        let msg = match item {
+            RawMessage::Text(text) => Message::Text(text.into()),
+            RawMessage::Binary(data) => Message::Binary(data),
+        };
+        Pin::new(&mut self.ws).start_send(msg).map_err(Error::from)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Pin::new(&mut self.ws).poll_flush(cx).map_err(Error::from)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Pin::new(&mut self.ws).poll_close(cx).map_err(Error::from)
+    }
+}
+
+pub(crate) async fn websocket(
+    config: &ClientConfig,
+    query: &ActorQuery,
+    credentials: &Credentials,
+    path: &str,
+    subprotocols: &[&str],
+) -> Result<RawWebSocket> {
+    let url = format!(
+        "{}/actors/raw/websocket/{}",
+        websocket_endpoint(&config.endpoint),
+        normalize_path(path)
+    );
+
+    debug!("Raw websocket: {}", url);
+
+    // Raw sockets are always JSON so the actor can read the parameters
+    let mut protocols = build_subprotocols(query, EncodingKind::Json, credentials.params.as_ref())?;
+    protocols.extend(subprotocols.iter().map(|p| p.to_string()));
+
+    let (ws, res) = open_websocket(config, &url, Some(&protocols), &credentials.headers).await?;
+
+    // Only report protocols the caller asked for, not the ones used to pass
+    // the actor query
+    let protocol = res
+        .headers()
+        .get(SEC_WEBSOCKET_PROTOCOL)
+        .and_then(|v| v.to_str().ok())
+        .filter(|p| subprotocols.contains(p))
+        .map(|p| p.to_string());
+
+    Ok(RawWebSocket { ws, protocol })
+}
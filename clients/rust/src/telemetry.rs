@@ -0,0 +1,88 @@
+//! Metrics recorded through the `metrics` facade when the `metrics` feature
+//! is enabled, no-ops otherwise.
+#![cfg_attr(not(feature = "metrics"), allow(unused_variables, dead_code))]
+
+use std::time::Duration;
+
+use crate::{common::EncodingKind, drivers::DriverStopReason};
+
+pub const ACTION_DURATION: &str = "rivetkit_client_action_duration_seconds";
+pub const ACTIONS_IN_FLIGHT: &str = "rivetkit_client_actions_in_flight";
+pub const QUEUE_DEPTH: &str = "rivetkit_client_queue_depth";
+pub const RECONNECTS: &str = "rivetkit_client_reconnects_total";
+pub const MESSAGES_SENT: &str = "rivetkit_client_messages_sent_total";
+pub const MESSAGES_RECEIVED: &str = "rivetkit_client_messages_received_total";
+pub const BYTES_SENT: &str = "rivetkit_client_bytes_sent_total";
+pub const BYTES_RECEIVED: &str = "rivetkit_client_bytes_received_total";
+pub const DESERIALIZATION_FAILURES: &str = "rivetkit_client_deserialization_failures_total";
+
+/// Transport label of stateless HTTP actions.
+pub const TRANSPORT_HTTP: &str = "http";
+
+#[cfg(feature = "metrics")]
+fn stop_reason_label(reason: DriverStopReason) -> &'static str {
+    match reason {
+        DriverStopReason::UserAborted => "user_aborted",
+        DriverStopReason::ServerDisconnect => "server_disconnect",
+        DriverStopReason::ServerError => "server_error",
+        DriverStopReason::TaskError => "task_error",
+        DriverStopReason::HeartbeatTimeout => "heartbeat_timeout",
+    }
+}
+
+pub fn action_completed(name: &str, transport: &'static str, elapsed: Duration) {
+    #[cfg(feature = "metrics")]
+    metrics::histogram!(ACTION_DURATION, "action" => name.to_string(), "transport" => transport)
+        .record(elapsed.as_secs_f64());
+}
+
+pub fn reconnect(reason: DriverStopReason) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!(RECONNECTS, "reason" => stop_reason_label(reason)).increment(1);
+}
+
+pub fn message_sent(encoding: EncodingKind, bytes: usize) {
+    #[cfg(feature = "metrics")]
+    {
+        metrics::counter!(MESSAGES_SENT, "encoding" => encoding.as_str()).increment(1);
+        metrics::counter!(BYTES_SENT, "encoding" => encoding.as_str()).increment(bytes as u64);
+    }
+}
+
+pub fn message_received(encoding: EncodingKind, bytes: usize) {
+    #[cfg(feature = "metrics")]
+    {
+        metrics::counter!(MESSAGES_RECEIVED, "encoding" => encoding.as_str()).increment(1);
+        metrics::counter!(BYTES_RECEIVED, "encoding" => encoding.as_str()).increment(bytes as u64);
+    }
+}
+
+pub fn deserialization_failed(encoding: EncodingKind) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!(DESERIALIZATION_FAILURES, "encoding" => encoding.as_str()).increment(1);
+}
+
+/// Adjusts the offline queue depth gauge by `delta` messages.
+pub fn queue_depth_changed(delta: isize) {
+    #[cfg(feature = "metrics")]
+    metrics::gauge!(QUEUE_DEPTH).increment(delta as f64);
+}
+
+/// Counts an action as in flight until dropped.
+pub struct InFlightGauge(());
+
+impl InFlightGauge {
+    pub fn start() -> Self {
+        #[cfg(feature = "metrics")]
+        metrics::gauge!(ACTIONS_IN_FLIGHT).increment(1.0);
+
+        Self(())
+    }
+}
+
+impl Drop for InFlightGauge {
+    fn drop(&mut self) {
+        #[cfg(feature = "metrics")]
+        metrics::gauge!(ACTIONS_IN_FLIGHT).decrement(1.0);
+    }
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{serve_hang, serve_once_with_request};
+use rivetkit_client::{
+    ActionOptions, CancellationToken, Client, EncodingKind, Error, GetOptions, TransportKind,
+};
+use serde_json::json;
+
+#[tokio::test]
+async fn stateless_action_times_out() {
+    let endpoint = serve_hang().await;
+
+    let client = Client::new(&endpoint, TransportKind::WebSocket, EncodingKind::Json);
+    let handle = client.get_for_id("actor-123", GetOptions::default()).unwrap();
+
+    let err = handle
+        .action_with_options(
+            "increment",
+            vec![],
+            ActionOptions {
+                timeout: Some(Duration::from_millis(100)),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, Error::Timeout), "got {:?}", err);
+}
+
+#[tokio::test]
+async fn stateless_action_is_cancelled() {
+    let endpoint = serve_hang().await;
+
+    let client = Client::new(&endpoint, TransportKind::WebSocket, EncodingKind::Json);
+    let handle = client.get_for_id("actor-123", GetOptions::default()).unwrap();
+
+    let cancel = CancellationToken::new();
+    let opts = ActionOptions {
+        cancel: cancel.clone(),
+        ..Default::default()
+    };
+
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        cancel.cancel();
+    });
+
+    let err = handle
+        .action_with_options("increment", vec![], opts)
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, Error::Cancelled), "got {:?}", err);
+}
+
+#[tokio::test]
+async fn stateless_action_sends_extra_headers() {
+    let body = serde_json::to_vec(&json!({ "o": null })).unwrap();
+    let (endpoint, req_rx) = serve_once_with_request("200 OK", "application/json", body).await;
+
+    let client = Client::new(&endpoint, TransportKind::WebSocket, EncodingKind::Json);
+    let handle = client.get_for_id("actor-123", GetOptions::default()).unwrap();
+
+    handle
+        .action_with_options(
+            "increment",
+            vec![],
+            ActionOptions {
+                headers: vec![("X-Request-Id".to_string(), "abc".to_string())],
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+    let request = req_rx.await.unwrap().to_ascii_lowercase();
+    assert!(request.contains("x-request-id: abc"), "{}", request);
+}
+
+#[tokio::test]
+async fn connection_action_times_out_while_disconnected() {
+    // Nothing listens on this port, so the connection never opens and the
+    // action stays queued
+    let client = Client::new("http://127.0.0.1:1", TransportKind::WebSocket, EncodingKind::Json);
+    let conn = client
+        .get_for_id("actor-123", GetOptions::default())
+        .unwrap()
+        .connect();
+
+    let err = conn
+        .action_with_options(
+            "increment",
+            vec![json!(1)],
+            ActionOptions {
+                timeout: Some(Duration::from_millis(100)),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, Error::Timeout), "got {:?}", err);
+
+    conn.disconnect().await;
+}
@@ -0,0 +1,104 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{connect_ws_actor, event_msg, recv_json, send_json, serve_once_with_request};
+use rivetkit_client::{actor_client, Client, EncodingKind, GetOptions, Result, TransportKind};
+use serde_json::json;
+use tokio::sync::mpsc;
+
+#[actor_client(name = "counter")]
+trait Counter {
+    async fn increment_by(&self, amount: i64, note: String) -> Result<i64>;
+
+    #[action(name = "getCount")]
+    async fn count(&self) -> Result<i64>;
+
+    #[event]
+    fn new_count(count: i64);
+}
+
+// A second client in scope, so generated items must not clash
+#[actor_client(name = "chat-room")]
+trait ChatRoom {
+    async fn send_message(&self, text: String) -> Result<()>;
+}
+
+/// Returns the request path & JSON body received by the server.
+fn split_request(request: &str) -> (&str, serde_json::Value) {
+    let path = request.split(' ').nth(1).unwrap();
+    let (_, body) = request.split_once("\r\n\r\n").unwrap();
+    (path, serde_json::from_str(body).unwrap())
+}
+
+#[tokio::test]
+async fn actor_client_calls_actions_through_handle() {
+    let body = serde_json::to_vec(&json!({ "o": 6 })).unwrap();
+    let (endpoint, req_rx) = serve_once_with_request("200 OK", "application/json", body).await;
+
+    let client = Client::new(&endpoint, TransportKind::WebSocket, EncodingKind::Json);
+    let handle = CounterClient::get(&client, vec!["room-1".to_string()], GetOptions::default()).unwrap();
+
+    assert_eq!(CounterClient::ACTOR_NAME, "counter");
+    assert_eq!(ChatRoomClient::ACTOR_NAME, "chat-room");
+    assert_eq!(handle.increment_by(5, "bonus".to_string()).await.unwrap(), 6);
+
+    let request = req_rx.await.unwrap();
+    let (path, body) = split_request(&request);
+    assert_eq!(path, "/actors/actions/incrementBy");
+    assert_eq!(body, json!({ "a": [5, "bonus"] }));
+
+    let query = r#"{"getForKey":{"name":"counter","key":["room-1"]}}"#;
+    assert!(request.contains(query), "{}", request);
+}
+
+#[tokio::test]
+async fn actor_client_uses_overridden_action_name() {
+    let body = serde_json::to_vec(&json!({ "o": 3 })).unwrap();
+    let (endpoint, req_rx) = serve_once_with_request("200 OK", "application/json", body).await;
+
+    let client = Client::new(&endpoint, TransportKind::WebSocket, EncodingKind::Json);
+    let handle = client.get_for_id("actor-123", GetOptions::default()).unwrap();
+    assert_eq!(handle.count().await.unwrap(), 3);
+
+    let request = req_rx.await.unwrap();
+    let (path, body) = split_request(&request);
+    assert_eq!(path, "/actors/actions/getCount");
+    assert_eq!(body, json!({ "a": [] }));
+}
+
+#[tokio::test]
+async fn actor_client_calls_actions_and_events_through_connection() {
+    let (_client, conn, mut ws) = connect_ws_actor().await;
+
+    let (tx, mut counts) = mpsc::unbounded_channel();
+    let _sub = conn
+        .on_new_count(move |count| {
+            tx.send(count.map(|(count,)| count).unwrap()).unwrap();
+        })
+        .await;
+    assert_eq!(
+        recv_json(&mut ws).await.unwrap(),
+        json!({ "b": { "sr": { "e": "newCount", "s": true } } })
+    );
+
+    let action = tokio::spawn({
+        let conn = conn.clone();
+        async move { conn.increment_by(5, "bonus".to_string()).await }
+    });
+    let req = recv_json(&mut ws).await.unwrap();
+    let id = req["b"]["ar"]["i"].clone();
+    assert_eq!(
+        req,
+        json!({ "b": { "ar": { "i": id, "n": "incrementBy", "a": [5, "bonus"] } } })
+    );
+    send_json(&mut ws, json!({ "b": { "ar": { "i": id, "o": 6 } } })).await;
+    assert_eq!(action.await.unwrap().unwrap(), 6);
+
+    send_json(&mut ws, event_msg("newCount", json!([6]))).await;
+    let count = tokio::time::timeout(Duration::from_secs(5), counts.recv())
+        .await
+        .expect("event was not delivered")
+        .unwrap();
+    assert_eq!(count, 6);
+}
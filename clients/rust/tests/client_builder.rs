@@ -0,0 +1,85 @@
+mod common;
+
+use std::time::Duration;
+
+use common::serve_once_with_request;
+use rivetkit_client::{Client, EncodingKind, Error, GetOptions, TransportKind};
+use serde_json::json;
+
+#[tokio::test]
+async fn builder_settings_are_applied_to_requests() {
+    let body = serde_json::to_vec(&json!({ "o": 1 })).unwrap();
+    let (endpoint, req_rx) = serve_once_with_request("200 OK", "application/json", body).await;
+
+    let client = Client::builder()
+        .endpoint(&endpoint)
+        .transport(TransportKind::Sse)
+        .encoding(EncodingKind::Json)
+        .header("X-Test-Header", "hello")
+        .user_agent_suffix("my-game/1.0")
+        .request_timeout(Duration::from_secs(5))
+        .build()
+        .unwrap();
+
+    assert_eq!(client.config().channel_capacity, 32);
+
+    let handle = client.get_for_id("actor-123", GetOptions::default()).unwrap();
+    handle.action("increment", vec![]).await.unwrap();
+
+    let request = req_rx.await.unwrap().to_ascii_lowercase();
+    assert!(request.contains("x-test-header: hello"), "{}", request);
+    assert!(request.contains("my-game/1.0"), "{}", request);
+}
+
+#[tokio::test]
+async fn builder_rejects_invalid_settings() {
+    let err = Client::builder()
+        .endpoint("http://127.0.0.1:1")
+        .channel_capacity(0)
+        .build()
+        .err()
+        .unwrap();
+
+    assert!(matches!(err, Error::Config(_)), "got {:?}", err);
+}
+
+#[tokio::test]
+async fn trailing_slash_is_trimmed_from_endpoint() {
+    let body = serde_json::to_vec(&json!({ "o": 1 })).unwrap();
+    let (endpoint, req_rx) = serve_once_with_request("200 OK", "application/json", body).await;
+
+    let client = Client::new(&format!("{}/", endpoint), TransportKind::WebSocket, EncodingKind::Json);
+    assert_eq!(client.config().endpoint, endpoint);
+
+    let handle = client.get_for_id("actor-123", GetOptions::default()).unwrap();
+    handle.action("increment", vec![]).await.unwrap();
+
+    let request = req_rx.await.unwrap();
+    assert!(request.starts_with("POST /actors/actions/increment "), "{}", request);
+}
+
+#[tokio::test]
+async fn builder_falls_back_to_env_vars() {
+    let body = serde_json::to_vec(&json!({ "o": 1 })).unwrap();
+    let (endpoint, req_rx) = serve_once_with_request("200 OK", "application/json", body).await;
+
+    std::env::set_var("RIVETKIT_ENDPOINT", format!("{}/", endpoint));
+    std::env::set_var("RIVETKIT_TRANSPORT", "sse");
+    std::env::set_var("RIVETKIT_ENCODING", "json");
+    let client = Client::builder().build();
+    std::env::remove_var("RIVETKIT_ENDPOINT");
+    std::env::remove_var("RIVETKIT_TRANSPORT");
+    std::env::remove_var("RIVETKIT_ENCODING");
+
+    let client = client.unwrap();
+    assert_eq!(client.config().endpoint, endpoint);
+    assert_eq!(client.config().transport_kind, TransportKind::Sse);
+    assert_eq!(client.config().encoding_kind, EncodingKind::Json);
+
+    let handle = client.get_for_id("actor-123", GetOptions::default()).unwrap();
+    handle.action("increment", vec![]).await.unwrap();
+
+    let request = req_rx.await.unwrap().to_ascii_lowercase();
+    assert!(request.starts_with("post /actors/actions/increment "), "{}", request);
+    assert!(request.contains("x-rivetkit-encoding: json"), "{}", request);
+}
@@ -0,0 +1,258 @@
+#![allow(dead_code)]
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+
+/// Serves a single canned HTTP response and returns the endpoint.
+pub async fn serve_once(status: &'static str, content_type: &'static str, body: Vec<u8>) -> String {
+    serve_once_with_request(status, content_type, body).await.0
+}
+
+/// Like [`serve_once`], also returning the raw request head received.
+pub async fn serve_once_with_request(
+    status: &'static str,
+    content_type: &'static str,
+    body: Vec<u8>,
+) -> (String, oneshot::Receiver<String>) {
+    let (req_tx, req_rx) = oneshot::channel();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+
+    tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+
+        // Drain the request, the body is small enough to arrive in one read
+        let mut buf = vec![0u8; 8192];
+        let n = socket.read(&mut buf).await.unwrap_or(0);
+        let _ = req_tx.send(String::from_utf8_lossy(&buf[..n]).into_owned());
+
+        let head = format!(
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            status,
+            content_type,
+            body.len()
+        );
+        socket.write_all(head.as_bytes()).await.unwrap();
+        socket.write_all(&body).await.unwrap();
+        socket.shutdown().await.ok();
+    });
+
+    (endpoint, req_rx)
+}
+
+/// Accepts connections without ever responding.
+pub async fn serve_hang() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+
+    tokio::spawn(async move {
+        let mut sockets = Vec::new();
+        while let Ok((socket, _)) = listener.accept().await {
+            sockets.push(socket);
+        }
+    });
+
+    endpoint
+}
+
+/// Endpoint that refuses every connection, so connections stay down.
+pub async fn closed_endpoint() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    format!("http://{}", listener.local_addr().unwrap())
+}
+
+/// Serves an actor over SSE, streaming the init message to every connection
+/// and answering each `/actors/message` request with `status` & a JSON
+/// `body`.
+pub async fn serve_sse_actor(status: &'static str, body: serde_json::Value) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    let body = body.to_string();
+
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let body = body.clone();
+            tokio::spawn(async move {
+                let mut buf = vec![0u8; 8192];
+                let n = socket.read(&mut buf).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..n]);
+
+                if request.starts_with("GET /actors/connect/sse") {
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\n\r\ndata: {}\n\n",
+                        init_msg("actor-123", "conn-1")
+                    );
+                    socket.write_all(response.as_bytes()).await.unwrap();
+
+                    // Keep the stream open until the client goes away
+                    while socket.read(&mut buf).await.unwrap_or(0) > 0 {}
+                    return;
+                }
+
+                let head = format!(
+                    "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    body.len()
+                );
+                socket.write_all(head.as_bytes()).await.unwrap();
+                socket.write_all(body.as_bytes()).await.unwrap();
+                socket.shutdown().await.ok();
+            });
+        }
+    });
+
+    endpoint
+}
+
+pub type ActorSocket = tokio_tungstenite::WebSocketStream<tokio::net::TcpStream>;
+
+/// Accepts actor WebSocket connections and hands each one to the test, which
+/// drives the JSON protocol with [`send_json`] / [`recv_json`].
+pub async fn serve_ws_actor() -> (String, tokio::sync::mpsc::UnboundedReceiver<ActorSocket>) {
+    let (endpoint, mut requests) = serve_ws_actor_with_protocols().await;
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        while let Some((_, ws)) = requests.recv().await {
+            if tx.send(ws).is_err() {
+                break;
+            }
+        }
+    });
+
+    (endpoint, rx)
+}
+
+/// Like [`serve_ws_actor`], also passing the subprotocols each client
+/// requested.
+pub async fn serve_ws_actor_with_protocols() -> (
+    String,
+    tokio::sync::mpsc::UnboundedReceiver<(Vec<String>, ActorSocket)>,
+) {
+    use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+    use tokio_tungstenite::tungstenite::http::HeaderValue;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        while let Ok((socket, _)) = listener.accept().await {
+            let mut protocols = Vec::new();
+            #[allow(clippy::result_large_err)]
+            let callback = |req: &Request, mut res: Response| -> Result<Response, ErrorResponse> {
+                if let Some(header) = req.headers().get("sec-websocket-protocol") {
+                    protocols = header
+                        .to_str()
+                        .unwrap()
+                        .split(',')
+                        .map(|p| p.trim().to_string())
+                        .collect();
+                    res.headers_mut()
+                        .insert("sec-websocket-protocol", HeaderValue::from_static("rivetkit"));
+                }
+                Ok(res)
+            };
+            if let Ok(ws) = tokio_tungstenite::accept_hdr_async(socket, callback).await {
+                if tx.send((protocols, ws)).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+
+    (endpoint, rx)
+}
+
+pub async fn send_json(ws: &mut ActorSocket, value: serde_json::Value) {
+    use futures_util::SinkExt;
+
+    ws.send(tokio_tungstenite::tungstenite::Message::Text(value.to_string().into()))
+        .await
+        .unwrap();
+}
+
+/// Next JSON message from the client, `None` once the socket is closed.
+pub async fn recv_json(ws: &mut ActorSocket) -> Option<serde_json::Value> {
+    use futures_util::StreamExt;
+    use tokio_tungstenite::tungstenite::Message;
+
+    while let Some(msg) = ws.next().await {
+        match msg {
+            Ok(Message::Text(text)) => return Some(serde_json::from_str(&text).unwrap()),
+            Ok(Message::Close(_)) | Err(_) => return None,
+            Ok(_) => continue,
+        }
+    }
+    None
+}
+
+pub fn init_msg(actor_id: &str, connection_id: &str) -> serde_json::Value {
+    serde_json::json!({ "b": { "i": { "ai": actor_id, "ci": connection_id, "ct": "token" } } })
+}
+
+pub fn event_msg(name: &str, args: serde_json::Value) -> serde_json::Value {
+    serde_json::json!({ "b": { "ev": { "n": name, "a": args } } })
+}
+
+/// Connects to a [`serve_ws_actor`] server and completes the handshake.
+pub async fn connect_ws_actor() -> (
+    rivetkit_client::Client,
+    rivetkit_client::connection::ActorConnection,
+    ActorSocket,
+) {
+    use rivetkit_client::{Client, EncodingKind, GetOptions, TransportKind};
+
+    let (endpoint, mut sockets) = serve_ws_actor().await;
+
+    let client = Client::new(&endpoint, TransportKind::WebSocket, EncodingKind::Json);
+    let conn = client
+        .get_for_id("actor-123", GetOptions::default())
+        .unwrap()
+        .connect();
+
+    let mut ws = sockets.recv().await.unwrap();
+    send_json(&mut ws, init_msg("actor-123", "conn-1")).await;
+    conn.ready().await.unwrap();
+
+    (client, conn, ws)
+}
+
+/// Waits for the connection to give up and returns the error it failed with.
+pub async fn wait_for_failure(
+    conn: &rivetkit_client::connection::ActorConnection,
+) -> rivetkit_client::ConnectionError {
+    use rivetkit_client::ConnectionStatus;
+
+    let mut status = conn.status();
+    let status = tokio::time::timeout(
+        std::time::Duration::from_secs(5),
+        status.wait_for(|s| matches!(s, ConnectionStatus::Failed { .. })),
+    )
+    .await
+    .expect("connection did not fail")
+    .unwrap();
+
+    let ConnectionStatus::Failed { error } = &*status else {
+        unreachable!();
+    };
+    error.clone()
+}
+
+/// Calls `name` without arguments on a separate task.
+pub fn spawn_action(
+    conn: &rivetkit_client::connection::ActorConnection,
+    name: &str,
+    idempotent: bool,
+) -> tokio::task::JoinHandle<rivetkit_client::Result<serde_json::Value>> {
+    let conn = conn.clone();
+    let name = name.to_string();
+    tokio::spawn(async move {
+        let opts = rivetkit_client::ActionOptions {
+            idempotent,
+            ..Default::default()
+        };
+        conn.action_with_options(&name, vec![], opts).await
+    })
+}
@@ -0,0 +1,188 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{
+    init_msg, recv_json, send_json, serve_once, serve_sse_actor, serve_ws_actor, wait_for_failure,
+};
+use rivetkit_client::{Client, ConnectionError, EncodingKind, Error, GetOptions, TransportKind};
+use serde_json::json;
+use tokio::sync::mpsc;
+
+#[tokio::test]
+async fn handshake_error_is_delivered_to_ready_and_on_error() {
+    let (endpoint, mut sockets) = serve_ws_actor().await;
+
+    let client = Client::new(&endpoint, TransportKind::WebSocket, EncodingKind::Json);
+    let handle = client.get_for_id("actor-123", GetOptions::default()).unwrap();
+    let conn = handle.connect();
+
+    let (tx, mut errors) = mpsc::unbounded_channel();
+    conn.on_error(move |err| {
+        tx.send(err.code().map(|c| c.to_string())).unwrap();
+    })
+    .await;
+
+    let mut ws = sockets.recv().await.unwrap();
+    send_json(
+        &mut ws,
+        json!({ "b": { "e": { "c": "unauthorized", "m": "Invalid token" } } }),
+    )
+    .await;
+
+    let err = tokio::time::timeout(Duration::from_secs(5), conn.ready())
+        .await
+        .unwrap()
+        .unwrap_err();
+    assert_eq!(err.code(), Some("unauthorized"));
+    assert_eq!(errors.recv().await.unwrap().as_deref(), Some("unauthorized"));
+
+    conn.disconnect().await;
+}
+
+#[tokio::test]
+async fn errors_after_handshake_only_reach_on_error() {
+    let (endpoint, mut sockets) = serve_ws_actor().await;
+
+    let client = Client::new(&endpoint, TransportKind::WebSocket, EncodingKind::Json);
+    let handle = client.get_for_id("actor-123", GetOptions::default()).unwrap();
+    let conn = handle.connect();
+
+    let (tx, mut errors) = mpsc::unbounded_channel();
+    conn.on_error(move |err| {
+        tx.send(err.code().map(|c| c.to_string())).unwrap();
+    })
+    .await;
+
+    let mut ws = sockets.recv().await.unwrap();
+    send_json(&mut ws, init_msg("actor-123", "conn-1")).await;
+    tokio::time::timeout(Duration::from_secs(5), conn.ready())
+        .await
+        .unwrap()
+        .unwrap();
+
+    send_json(
+        &mut ws,
+        json!({ "b": { "e": { "c": "internal_error", "m": "Actor crashed" } } }),
+    )
+    .await;
+
+    let code = tokio::time::timeout(Duration::from_secs(5), errors.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(code.as_deref(), Some("internal_error"));
+    conn.ready().await.unwrap();
+
+    conn.disconnect().await;
+}
+
+#[tokio::test]
+async fn fatal_handshake_error_fails_pending_actions() {
+    let (endpoint, mut sockets) = serve_ws_actor().await;
+
+    let client = Client::new(&endpoint, TransportKind::WebSocket, EncodingKind::Json);
+    let handle = client.get_for_id("actor-123", GetOptions::default()).unwrap();
+    let conn = handle.connect();
+
+    let mut ws = sockets.recv().await.unwrap();
+
+    // Queued until the handshake completes
+    let pending = tokio::spawn({
+        let conn = conn.clone();
+        async move { conn.action("increment", vec![json!(1)]).await }
+    });
+
+    send_json(
+        &mut ws,
+        json!({ "b": { "e": { "c": "unauthorized", "m": "Invalid token" } } }),
+    )
+    .await;
+
+    let error = wait_for_failure(&conn).await;
+    assert_eq!(error.code(), Some("unauthorized"));
+
+    let err = pending.await.unwrap().unwrap_err();
+    assert_eq!(err.code(), Some("unauthorized"));
+
+    // Failed connections reject new actions immediately
+    let err = conn.action("increment", vec![]).await.unwrap_err();
+    assert_eq!(err.code(), Some("unauthorized"));
+    assert_eq!(conn.ready().await.unwrap_err().code(), Some("unauthorized"));
+
+    // The client closed the socket and does not reconnect
+    while recv_json(&mut ws).await.is_some() {}
+    assert!(
+        tokio::time::timeout(Duration::from_millis(1500), sockets.recv())
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn rejected_upgrade_is_fatal() {
+    let endpoint = serve_once("403 Forbidden", "text/plain", b"nope".to_vec()).await;
+
+    let client = Client::new(&endpoint, TransportKind::WebSocket, EncodingKind::Json);
+    let handle = client.get_for_id("actor-123", GetOptions::default()).unwrap();
+    let conn = handle.connect();
+
+    assert_eq!(
+        wait_for_failure(&conn).await,
+        ConnectionError::HttpStatus {
+            status: 403,
+            body: "nope".into(),
+        }
+    );
+
+    let err = conn.action("increment", vec![]).await.unwrap_err();
+    assert!(matches!(err, Error::HttpStatus { status: 403, .. }), "{:?}", err);
+}
+
+#[tokio::test]
+async fn invalid_endpoint_is_fatal() {
+    let client = Client::new("not a url", TransportKind::WebSocket, EncodingKind::Json);
+    let handle = client.get_for_id("actor-123", GetOptions::default()).unwrap();
+    let conn = handle.connect();
+
+    let error = wait_for_failure(&conn).await;
+    assert!(matches!(error, ConnectionError::Config(_)), "{:?}", error);
+}
+
+#[tokio::test]
+async fn rejected_sse_stream_is_fatal() {
+    let endpoint = serve_once("401 Unauthorized", "text/plain", b"nope".to_vec()).await;
+
+    let client = Client::new(&endpoint, TransportKind::Sse, EncodingKind::Json);
+    let handle = client.get_for_id("actor-123", GetOptions::default()).unwrap();
+    let conn = handle.connect();
+
+    let error = wait_for_failure(&conn).await;
+    assert!(
+        matches!(error, ConnectionError::HttpStatus { status: 401, .. }),
+        "{:?}",
+        error
+    );
+}
+
+#[tokio::test]
+async fn rejected_sse_message_rejects_action() {
+    let endpoint = serve_sse_actor(
+        "401 Unauthorized",
+        json!({ "c": "unauthorized", "m": "Connection token expired" }),
+    )
+    .await;
+
+    let client = Client::new(&endpoint, TransportKind::Sse, EncodingKind::Json);
+    let conn = client
+        .get_for_id("actor-123", GetOptions::default())
+        .unwrap()
+        .connect();
+    conn.ready().await.unwrap();
+
+    let err = tokio::time::timeout(Duration::from_secs(5), conn.action("increment", vec![]))
+        .await
+        .expect("action never resolved")
+        .unwrap_err();
+    assert_eq!(err.code(), Some("unauthorized"), "{:?}", err);
+}
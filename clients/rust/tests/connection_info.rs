@@ -0,0 +1,63 @@
+mod common;
+
+use std::time::SystemTime;
+
+use common::{init_msg, recv_json, send_json, serve_ws_actor};
+use rivetkit_client::{Client, EncodingKind, GetOptions, TransportKind};
+use serde_json::json;
+
+#[tokio::test]
+async fn connect_and_wait_returns_connection_info() {
+    let (endpoint, mut sockets) = serve_ws_actor().await;
+
+    let client = Client::new(&endpoint, TransportKind::WebSocket, EncodingKind::Json);
+    let handle = client.get_for_id("actor-123", GetOptions::default()).unwrap();
+
+    let before = SystemTime::now();
+    let server = tokio::spawn(async move {
+        let mut ws = sockets.recv().await.unwrap();
+        send_json(&mut ws, init_msg("actor-123", "conn-1")).await;
+        ws
+    });
+
+    let conn = handle.connect_and_wait().await.unwrap();
+    let _ws = server.await.unwrap();
+
+    let info = conn.ready().await.unwrap();
+    assert_eq!(info.actor_id, "actor-123");
+    assert_eq!(info.connection_id, "conn-1");
+    assert_eq!(info.transport, TransportKind::WebSocket);
+    assert_eq!(info.encoding, EncodingKind::Json);
+    assert!(info.connected_since >= before);
+
+    assert_eq!(conn.actor_id().as_deref(), Some("actor-123"));
+    assert_eq!(conn.connection_id().as_deref(), Some("conn-1"));
+    assert_eq!(conn.connected_since(), Some(info.connected_since));
+    assert_eq!(conn.transport(), TransportKind::WebSocket);
+    assert_eq!(conn.encoding(), EncodingKind::Json);
+
+    conn.disconnect().await;
+    assert_eq!(conn.actor_id(), None);
+}
+
+#[tokio::test]
+async fn connect_and_wait_fails_on_handshake_error() {
+    let (endpoint, mut sockets) = serve_ws_actor().await;
+
+    let client = Client::new(&endpoint, TransportKind::WebSocket, EncodingKind::Json);
+    let handle = client.get_for_id("actor-123", GetOptions::default()).unwrap();
+
+    tokio::spawn(async move {
+        let mut ws = sockets.recv().await.unwrap();
+        send_json(
+            &mut ws,
+            json!({ "b": { "e": { "c": "unauthorized", "m": "Invalid token" } } }),
+        )
+        .await;
+        // Until the client closes the socket
+        while recv_json(&mut ws).await.is_some() {}
+    });
+
+    let err = handle.connect_and_wait().await.unwrap_err();
+    assert_eq!(err.code(), Some("unauthorized"));
+}
@@ -0,0 +1,125 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{init_msg, send_json, serve_ws_actor};
+use futures_util::SinkExt;
+use rivetkit_client::{
+    Client, ConnectionStatus, DisconnectReason, DriverStopReason, EncodingKind, GetOptions,
+    TransportKind,
+};
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::protocol::{frame::coding::CloseCode, CloseFrame};
+use tokio_tungstenite::tungstenite::Message;
+
+async fn next_status(rx: &mut mpsc::UnboundedReceiver<ConnectionStatus>) -> ConnectionStatus {
+    tokio::time::timeout(Duration::from_secs(5), rx.recv())
+        .await
+        .expect("status did not change")
+        .unwrap()
+}
+
+#[tokio::test]
+async fn status_tracks_connection_lifecycle() {
+    let (endpoint, mut sockets) = serve_ws_actor().await;
+
+    let client = Client::new(&endpoint, TransportKind::WebSocket, EncodingKind::Json);
+    let handle = client.get_for_id("actor-123", GetOptions::default()).unwrap();
+    let conn = handle.connect();
+
+    let status = conn.status();
+    assert_eq!(*status.borrow(), ConnectionStatus::Connecting);
+
+    let (tx, mut statuses) = mpsc::unbounded_channel();
+    conn.on_status_change(move |status| {
+        tx.send(status.clone()).unwrap();
+    })
+    .await;
+
+    let mut ws = sockets.recv().await.unwrap();
+    send_json(&mut ws, init_msg("actor-123", "conn-1")).await;
+    assert_eq!(
+        next_status(&mut statuses).await,
+        ConnectionStatus::Connected {
+            actor_id: "actor-123".into(),
+            connection_id: "conn-1".into(),
+        }
+    );
+
+    ws.send(Message::Close(Some(CloseFrame {
+        code: CloseCode::Library(4001),
+        reason: "kicked".into(),
+    })))
+    .await
+    .unwrap();
+
+    assert_eq!(
+        next_status(&mut statuses).await,
+        ConnectionStatus::Disconnected {
+            reason: DisconnectReason {
+                stop_reason: DriverStopReason::ServerDisconnect,
+                close_code: Some(4001),
+                close_reason: Some("kicked".into()),
+                http_status: None,
+            },
+        }
+    );
+    assert_eq!(
+        next_status(&mut statuses).await,
+        ConnectionStatus::Reconnecting {
+            attempt: 1,
+            next_delay: Duration::from_secs(1),
+        }
+    );
+
+    let mut ws = sockets.recv().await.unwrap();
+    send_json(&mut ws, init_msg("actor-123", "conn-2")).await;
+    assert_eq!(
+        next_status(&mut statuses).await,
+        ConnectionStatus::Connected {
+            actor_id: "actor-123".into(),
+            connection_id: "conn-2".into(),
+        }
+    );
+    assert!(matches!(&*status.borrow(), ConnectionStatus::Connected { connection_id, .. } if connection_id == "conn-2"));
+
+    conn.disconnect().await;
+    assert_eq!(
+        next_status(&mut statuses).await,
+        ConnectionStatus::Disconnected {
+            reason: DriverStopReason::UserAborted.into(),
+        }
+    );
+}
+
+#[tokio::test]
+async fn status_reports_reconnect_backoff() {
+    // Nothing listens on this endpoint, every attempt fails
+    let port = portpicker::pick_unused_port().unwrap();
+    let client = Client::new(
+        &format!("http://127.0.0.1:{}", port),
+        TransportKind::WebSocket,
+        EncodingKind::Json,
+    );
+    let handle = client.get_for_id("actor-123", GetOptions::default()).unwrap();
+    let conn = handle.connect();
+
+    let mut status = conn.status();
+    tokio::time::timeout(
+        Duration::from_secs(5),
+        status.wait_for(|s| matches!(s, ConnectionStatus::Reconnecting { .. })),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+
+    assert_eq!(
+        *status.borrow(),
+        ConnectionStatus::Reconnecting {
+            attempt: 1,
+            next_delay: Duration::from_secs(1),
+        }
+    );
+
+    conn.disconnect().await;
+}
@@ -0,0 +1,150 @@
+mod common;
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+use common::{init_msg, send_json, serve_once_with_request, serve_sse_actor, serve_ws_actor_with_protocols};
+use futures_util::future::BoxFuture;
+use rivetkit_client::{
+    Client, ConnectionError, ConnectionStatus, CredentialProvider, EncodingKind, Error, GetOptions,
+    RequestContext, RequestKind, TransportKind,
+};
+use serde_json::{json, Value};
+
+/// Hands out a new token on every call, or fails if `fail` is set.
+/// `fail_messages` only fails the headers of SSE message requests.
+#[derive(Default)]
+struct Tokens {
+    issued: AtomicU32,
+    fail: bool,
+    fail_messages: bool,
+}
+
+impl CredentialProvider for Tokens {
+    fn params<'a>(&'a self, _ctx: &'a RequestContext) -> BoxFuture<'a, rivetkit_client::Result<Option<Value>>> {
+        Box::pin(async move {
+            if self.fail {
+                return Err(Error::Config("token endpoint unreachable".to_string()));
+            }
+            let n = self.issued.fetch_add(1, Ordering::SeqCst) + 1;
+            Ok(Some(json!({ "token": format!("t{}", n) })))
+        })
+    }
+
+    fn headers<'a>(&'a self, ctx: &'a RequestContext) -> BoxFuture<'a, rivetkit_client::Result<Vec<(String, String)>>> {
+        Box::pin(async move {
+            if self.fail_messages && ctx.kind == RequestKind::Message {
+                return Err(Error::Config("token expired".to_string()));
+            }
+            let kind = format!("{:?}", ctx.kind);
+            Ok(vec![("X-Request-Kind".to_string(), kind)])
+        })
+    }
+}
+
+fn client(endpoint: &str, tokens: Tokens) -> Client {
+    client_with_transport(endpoint, TransportKind::WebSocket, tokens)
+}
+
+fn client_with_transport(endpoint: &str, transport: TransportKind, tokens: Tokens) -> Client {
+    Client::builder()
+        .endpoint(endpoint)
+        .transport(transport)
+        .encoding(EncodingKind::Json)
+        .credential_provider(tokens)
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn stateless_action_uses_provided_credentials() {
+    let body = serde_json::to_vec(&json!({ "o": null })).unwrap();
+    let (endpoint, req_rx) = serve_once_with_request("200 OK", "application/json", body).await;
+
+    let client = client(&endpoint, Tokens::default());
+    let handle = client
+        .get_for_id(
+            "actor-123",
+            GetOptions {
+                params: Some(json!({ "token": "static" })),
+            },
+        )
+        .unwrap();
+    handle.action("increment", vec![]).await.unwrap();
+
+    let request = req_rx.await.unwrap().to_ascii_lowercase();
+    assert!(request.contains(r#"{"token":"t1"}"#), "{}", request);
+    assert!(!request.contains("static"), "{}", request);
+    assert!(request.contains("x-request-kind: action"), "{}", request);
+}
+
+#[tokio::test]
+async fn reconnect_refreshes_credentials() {
+    let (endpoint, mut requests) = serve_ws_actor_with_protocols().await;
+
+    let client = client(&endpoint, Tokens::default());
+    let conn = client
+        .get_for_id("actor-123", GetOptions::default())
+        .unwrap()
+        .connect();
+
+    let params = |token: &str| {
+        let params = json!({ "token": token }).to_string();
+        format!("conn_params.{}", urlencoding::encode(&params))
+    };
+
+    let (protocols, mut ws) = requests.recv().await.unwrap();
+    assert!(protocols.contains(&params("t1")), "{:?}", protocols);
+    send_json(&mut ws, init_msg("actor-123", "conn-1")).await;
+    conn.ready().await.unwrap();
+
+    drop(ws);
+
+    let (protocols, _ws) = requests.recv().await.unwrap();
+    assert!(protocols.contains(&params("t2")), "{:?}", protocols);
+}
+
+#[tokio::test]
+async fn provider_failure_is_an_auth_error() {
+    let (endpoint, _requests) = serve_ws_actor_with_protocols().await;
+    let tokens = Tokens {
+        fail: true,
+        ..Default::default()
+    };
+
+    let client = client(&endpoint, tokens);
+    let handle = client.get_for_id("actor-123", GetOptions::default()).unwrap();
+    let err = handle.action("increment", vec![]).await.unwrap_err();
+    assert!(matches!(err, Error::Auth(_)), "{:?}", err);
+
+    // Connections fail instead of retrying
+    let conn = handle.connect();
+    let err = conn.ready().await.unwrap_err();
+    assert!(matches!(err, Error::Auth(_)), "{:?}", err);
+    assert!(matches!(
+        &*conn.status().borrow(),
+        ConnectionStatus::Failed { error: ConnectionError::Auth(_) }
+    ));
+}
+
+#[tokio::test]
+async fn sse_message_credential_failure_rejects_action() {
+    let endpoint = serve_sse_actor("200 OK", json!({})).await;
+    let tokens = Tokens {
+        fail_messages: true,
+        ..Default::default()
+    };
+
+    let client = client_with_transport(&endpoint, TransportKind::Sse, tokens);
+    let conn = client
+        .get_for_id("actor-123", GetOptions::default())
+        .unwrap()
+        .connect();
+    conn.ready().await.unwrap();
+
+    let err = tokio::time::timeout(Duration::from_secs(5), conn.action("increment", vec![]))
+        .await
+        .expect("action never resolved")
+        .unwrap_err();
+    assert!(matches!(err, Error::Auth(_)), "{:?}", err);
+}
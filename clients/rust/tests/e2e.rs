@@ -191,7 +191,7 @@ async fn e2e() {
         .unwrap();
     let conn = counter.connect();
 
-    conn.on_event("newCount", |x| {
+    let _sub = conn.on_event("newCount", |x| {
         info!("Received newCount event: {:?}", x);
     }).await;
     
@@ -0,0 +1,121 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use common::{connect_ws_actor, event_msg, recv_json, send_json};
+use rivetkit_client::{Error, Subscription};
+use serde_json::json;
+use tokio::sync::{mpsc, Notify};
+
+#[tokio::test]
+async fn slow_listener_does_not_stall_actions() {
+    let (_client, conn, mut ws) = connect_ws_actor().await;
+
+    let gate = Arc::new(Notify::new());
+    let (tx, mut handled) = mpsc::unbounded_channel();
+    let _sub = conn
+        .on_event_async("newCount", {
+            let gate = gate.clone();
+            move |event| {
+                let gate = gate.clone();
+                let tx = tx.clone();
+                async move {
+                    gate.notified().await;
+                    tx.send(event.args).unwrap();
+                }
+            }
+        })
+        .await;
+    recv_json(&mut ws).await.unwrap();
+
+    send_json(&mut ws, event_msg("newCount", json!([1]))).await;
+    send_json(&mut ws, event_msg("newCount", json!([2]))).await;
+
+    let action = tokio::spawn({
+        let conn = conn.clone();
+        async move { conn.action("getCount", vec![]).await }
+    });
+    let req = recv_json(&mut ws).await.unwrap();
+    let id = req["b"]["ar"]["i"].clone();
+    send_json(&mut ws, json!({ "b": { "ar": { "i": id, "o": 5 } } })).await;
+
+    let output = tokio::time::timeout(Duration::from_secs(5), action)
+        .await
+        .expect("action stalled behind the listener")
+        .unwrap()
+        .unwrap();
+    assert_eq!(output, json!(5));
+
+    // Events are still delivered in order once the listener resumes
+    gate.notify_one();
+    assert_eq!(handled.recv().await.unwrap(), vec![json!(1)]);
+    gate.notify_one();
+    assert_eq!(handled.recv().await.unwrap(), vec![json!(2)]);
+}
+
+#[tokio::test]
+async fn panicking_listener_is_reported() {
+    let (_client, conn, mut ws) = connect_ws_actor().await;
+
+    let (err_tx, mut errors) = mpsc::unbounded_channel();
+    conn.on_error(move |err| {
+        if let Error::EventCallbackPanicked { .. } = err {
+            err_tx.send(err.to_string()).unwrap();
+        }
+    })
+    .await;
+
+    let (tx, mut handled) = mpsc::unbounded_channel();
+    let _sub = conn
+        .on_event("newCount", move |args| {
+            if args[0] == json!(1) {
+                panic!("bad count");
+            }
+            tx.send(args.clone()).unwrap();
+        })
+        .await;
+    recv_json(&mut ws).await.unwrap();
+
+    send_json(&mut ws, event_msg("newCount", json!([1]))).await;
+    send_json(&mut ws, event_msg("newCount", json!([2]))).await;
+
+    assert_eq!(
+        errors.recv().await.unwrap(),
+        "listener for event newCount panicked: bad count"
+    );
+    assert_eq!(handled.recv().await.unwrap(), vec![json!(2)]);
+}
+
+#[tokio::test]
+async fn listeners_can_subscribe_reentrantly() {
+    let (_client, conn, mut ws) = connect_ws_actor().await;
+
+    let nested: Arc<Mutex<Vec<Subscription>>> = Arc::default();
+    let _sub = conn
+        .on_event_async("newCount", {
+            let conn = Arc::downgrade(&conn);
+            let nested = nested.clone();
+            move |_| {
+                let conn = conn.clone();
+                let nested = nested.clone();
+                async move {
+                    let conn = conn.upgrade().unwrap();
+                    let sub = conn.on_event("newMessage", |_| {}).await;
+                    nested.lock().unwrap().push(sub);
+                }
+            }
+        })
+        .await;
+    assert_eq!(
+        recv_json(&mut ws).await.unwrap(),
+        json!({ "b": { "sr": { "e": "newCount", "s": true } } })
+    );
+
+    send_json(&mut ws, event_msg("newCount", json!([1]))).await;
+    assert_eq!(
+        recv_json(&mut ws).await.unwrap(),
+        json!({ "b": { "sr": { "e": "newMessage", "s": true } } })
+    );
+    assert_eq!(nested.lock().unwrap().len(), 1);
+}
@@ -0,0 +1,160 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{connect_ws_actor, event_msg, recv_json, send_json, ActorSocket};
+use futures_util::StreamExt;
+use rivetkit_client::{
+    connection::ActorConnection, Event, EventStream, EventStreamError, EventStreamOptions,
+    OverflowPolicy, Subscription,
+};
+use serde_json::json;
+use tokio::sync::mpsc;
+
+fn event(name: &str, n: i64) -> Event {
+    Event {
+        name: name.into(),
+        args: vec![json!(n)],
+    }
+}
+
+async fn next(stream: &mut EventStream) -> Option<Result<Event, EventStreamError>> {
+    tokio::time::timeout(Duration::from_secs(5), stream.next())
+        .await
+        .expect("stream did not yield")
+}
+
+/// Subscribes to a `done` marker, events sent before it have been handed to
+/// the streams once it is received.
+async fn done_marker(conn: &ActorConnection, ws: &mut ActorSocket) -> (Subscription, mpsc::UnboundedReceiver<()>) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let sub = conn
+        .on_event("done", move |_| {
+            tx.send(()).ok();
+        })
+        .await;
+    recv_json(ws).await.unwrap();
+    (sub, rx)
+}
+
+async fn send_counts(ws: &mut ActorSocket, counts: std::ops::RangeInclusive<i64>) {
+    for n in counts {
+        send_json(ws, event_msg("newCount", json!([n]))).await;
+    }
+    send_json(ws, event_msg("done", json!([]))).await;
+}
+
+#[tokio::test]
+async fn streams_named_and_all_events() {
+    let (_client, conn, mut ws) = connect_ws_actor().await;
+
+    let mut counts = conn.events("newCount").await;
+    assert_eq!(
+        recv_json(&mut ws).await.unwrap(),
+        json!({ "b": { "sr": { "e": "newCount", "s": true } } })
+    );
+    let mut all = conn.all_events().await;
+
+    send_json(&mut ws, event_msg("newCount", json!([1]))).await;
+    send_json(&mut ws, event_msg("unsubscribed", json!([]))).await;
+
+    assert_eq!(next(&mut counts).await.unwrap().unwrap(), event("newCount", 1));
+    assert_eq!(next(&mut all).await.unwrap().unwrap(), event("newCount", 1));
+
+    // Dropping the stream unsubscribes
+    drop(counts);
+    assert_eq!(
+        recv_json(&mut ws).await.unwrap(),
+        json!({ "b": { "sr": { "e": "newCount", "s": false } } })
+    );
+
+    // Streams end once the connection is disposed
+    conn.disconnect().await;
+    assert!(next(&mut all).await.is_none());
+}
+
+#[tokio::test]
+async fn error_policy_reports_lag() {
+    let (_client, conn, mut ws) = connect_ws_actor().await;
+
+    let mut counts = conn
+        .events_with_options(
+            "newCount",
+            EventStreamOptions {
+                buffer: 2,
+                overflow: OverflowPolicy::Error,
+            },
+        )
+        .await;
+    recv_json(&mut ws).await.unwrap();
+    let (_done, mut done) = done_marker(&conn, &mut ws).await;
+
+    send_counts(&mut ws, 1..=4).await;
+    done.recv().await.unwrap();
+
+    assert_eq!(next(&mut counts).await.unwrap(), Err(EventStreamError::Lagged(2)));
+    assert_eq!(next(&mut counts).await.unwrap().unwrap(), event("newCount", 3));
+    assert_eq!(next(&mut counts).await.unwrap().unwrap(), event("newCount", 4));
+}
+
+#[tokio::test]
+async fn drop_policies_discard_events() {
+    let (_client, conn, mut ws) = connect_ws_actor().await;
+
+    let mut newest = conn
+        .events_with_options(
+            "newCount",
+            EventStreamOptions {
+                buffer: 2,
+                overflow: OverflowPolicy::DropNewest,
+            },
+        )
+        .await;
+    recv_json(&mut ws).await.unwrap();
+    let mut oldest = conn
+        .events_with_options(
+            "newCount",
+            EventStreamOptions {
+                buffer: 2,
+                overflow: OverflowPolicy::DropOldest,
+            },
+        )
+        .await;
+    let (_done, mut done) = done_marker(&conn, &mut ws).await;
+
+    send_counts(&mut ws, 1..=4).await;
+    done.recv().await.unwrap();
+
+    assert_eq!(next(&mut newest).await.unwrap().unwrap(), event("newCount", 1));
+    assert_eq!(next(&mut newest).await.unwrap().unwrap(), event("newCount", 2));
+    assert_eq!(next(&mut oldest).await.unwrap().unwrap(), event("newCount", 3));
+    assert_eq!(next(&mut oldest).await.unwrap().unwrap(), event("newCount", 4));
+}
+
+#[tokio::test]
+async fn block_policy_applies_backpressure() {
+    let (_client, conn, mut ws) = connect_ws_actor().await;
+
+    let mut counts = conn
+        .events_with_options(
+            "newCount",
+            EventStreamOptions {
+                buffer: 1,
+                overflow: OverflowPolicy::Block,
+            },
+        )
+        .await;
+    recv_json(&mut ws).await.unwrap();
+    let (_done, mut done) = done_marker(&conn, &mut ws).await;
+
+    send_counts(&mut ws, 1..=3).await;
+
+    // The connection waits for the stream before handling the marker
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(done.try_recv().is_err());
+
+    for n in 1..=3 {
+        assert_eq!(next(&mut counts).await.unwrap().unwrap(), event("newCount", n));
+    }
+    done.recv().await.unwrap();
+}
@@ -0,0 +1,121 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{init_msg, recv_json, send_json, serve_ws_actor};
+use rivetkit_client::{
+    connection::ActorConnection, Client, ConnectionStatus, DriverStopReason, EncodingKind,
+    GetOptions, HeartbeatPolicy, TransportKind,
+};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+fn connect(endpoint: &str, transport: TransportKind, heartbeat: HeartbeatPolicy) -> (Client, ActorConnection) {
+    let client = Client::builder()
+        .endpoint(endpoint)
+        .transport(transport)
+        .encoding(EncodingKind::Json)
+        .heartbeat(heartbeat)
+        .build()
+        .unwrap();
+    let conn = client
+        .get_for_id("actor-123", GetOptions::default())
+        .unwrap()
+        .connect();
+
+    (client, conn)
+}
+
+async fn wait_for_disconnect(statuses: &mut mpsc::UnboundedReceiver<ConnectionStatus>) -> DriverStopReason {
+    loop {
+        let status = tokio::time::timeout(Duration::from_secs(5), statuses.recv())
+            .await
+            .expect("dead connection was not detected")
+            .unwrap();
+        if let ConnectionStatus::Disconnected { reason } = status {
+            return reason.stop_reason;
+        }
+    }
+}
+
+async fn statuses(conn: &ActorConnection) -> mpsc::UnboundedReceiver<ConnectionStatus> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    conn.on_status_change(move |status| {
+        tx.send(status.clone()).ok();
+    })
+    .await;
+    rx
+}
+
+fn ws_heartbeat() -> HeartbeatPolicy {
+    HeartbeatPolicy {
+        ping_interval: Some(Duration::from_millis(50)),
+        pong_timeout: Duration::from_millis(50),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn missing_pong_drops_websocket() {
+    let (endpoint, mut sockets) = serve_ws_actor().await;
+    let (_client, conn) = connect(&endpoint, TransportKind::WebSocket, ws_heartbeat());
+    let mut statuses = statuses(&conn).await;
+
+    // The server never reads, so pings go unanswered
+    let mut ws = sockets.recv().await.unwrap();
+    send_json(&mut ws, init_msg("actor-123", "conn-1")).await;
+    conn.ready().await.unwrap();
+
+    assert_eq!(wait_for_disconnect(&mut statuses).await, DriverStopReason::HeartbeatTimeout);
+}
+
+#[tokio::test]
+async fn answered_pings_keep_websocket_open() {
+    let (endpoint, mut sockets) = serve_ws_actor().await;
+    let (_client, conn) = connect(&endpoint, TransportKind::WebSocket, ws_heartbeat());
+
+    let mut ws = sockets.recv().await.unwrap();
+    send_json(&mut ws, init_msg("actor-123", "conn-1")).await;
+    conn.ready().await.unwrap();
+
+    // Reading answers pings
+    tokio::spawn(async move { while recv_json(&mut ws).await.is_some() {} });
+
+    tokio::time::sleep(Duration::from_millis(400)).await;
+    assert!(matches!(*conn.status().borrow(), ConnectionStatus::Connected { .. }));
+}
+
+#[tokio::test]
+async fn silent_sse_stream_is_dropped() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+
+    tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut buf = [0; 4096];
+        let _ = socket.read(&mut buf).await.unwrap();
+
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\n\r\ndata: {}\n\n",
+            init_msg("actor-123", "conn-1")
+        );
+        socket.write_all(response.as_bytes()).await.unwrap();
+
+        // Stay connected without sending anything
+        let _ = socket.read(&mut buf).await;
+    });
+
+    let (_client, conn) = connect(
+        &endpoint,
+        TransportKind::Sse,
+        HeartbeatPolicy {
+            sse_idle_timeout: Some(Duration::from_millis(100)),
+            ..Default::default()
+        },
+    );
+    let mut statuses = statuses(&conn).await;
+    conn.ready().await.unwrap();
+
+    assert_eq!(wait_for_disconnect(&mut statuses).await, DriverStopReason::HeartbeatTimeout);
+}
@@ -0,0 +1,96 @@
+mod common;
+
+use common::serve_once;
+use rivetkit_client::{Client, CreateOptions, EncodingKind, Error, GetOptions, TransportKind};
+use serde_json::json;
+
+#[tokio::test]
+async fn stateless_action_decodes_json_error() {
+    let body = serde_json::to_vec(&json!({
+        "c": "unauthorized",
+        "m": "Unauthorized access",
+        "md": { "reason": "expired" },
+    }))
+    .unwrap();
+    let endpoint = serve_once("401 Unauthorized", "application/json", body).await;
+
+    let client = Client::new(&endpoint, TransportKind::WebSocket, EncodingKind::Json);
+    let handle = client.get_for_id("actor-123", GetOptions::default()).unwrap();
+
+    let err = handle.action("increment", vec![json!(1)]).await.unwrap_err();
+
+    #[derive(serde::Deserialize)]
+    struct Metadata {
+        reason: String,
+    }
+
+    assert_eq!(err.code(), Some("unauthorized"));
+    let Error::Actor { message, .. } = &err else {
+        panic!("expected actor error, got {:?}", err);
+    };
+    assert_eq!(message, "Unauthorized access");
+    let metadata = err.metadata::<Metadata>().unwrap().unwrap();
+    assert_eq!(metadata.reason, "expired");
+}
+
+#[tokio::test]
+async fn stateless_action_decodes_cbor_error() {
+    let body = serde_cbor::to_vec(&json!({
+        "c": "action_not_found",
+        "m": "Action not found",
+    }))
+    .unwrap();
+    let endpoint = serve_once("400 Bad Request", "application/octet-stream", body).await;
+
+    let client = Client::new(&endpoint, TransportKind::WebSocket, EncodingKind::Cbor);
+    let handle = client.get_for_id("actor-123", GetOptions::default()).unwrap();
+
+    let err = handle.action("missing", vec![]).await.unwrap_err();
+
+    assert_eq!(err.code(), Some("action_not_found"));
+    assert!(err.metadata::<serde_json::Value>().unwrap().is_none());
+}
+
+#[tokio::test]
+async fn unstructured_error_falls_back_to_status() {
+    let endpoint = serve_once(
+        "502 Bad Gateway",
+        "text/html",
+        b"<html>bad gateway</html>".to_vec(),
+    )
+    .await;
+
+    let client = Client::new(&endpoint, TransportKind::WebSocket, EncodingKind::Json);
+    let handle = client.get_for_id("actor-123", GetOptions::default()).unwrap();
+
+    let err = handle.action("increment", vec![]).await.unwrap_err();
+
+    let Error::HttpStatus { status, body } = err else {
+        panic!("expected http status error, got {:?}", err);
+    };
+    assert_eq!(status, 502);
+    assert_eq!(body, "<html>bad gateway</html>");
+}
+
+#[tokio::test]
+async fn unstructured_create_error_is_manager_error() {
+    let endpoint = serve_once(
+        "502 Bad Gateway",
+        "text/html",
+        b"<html>bad gateway</html>".to_vec(),
+    )
+    .await;
+
+    let client = Client::new(&endpoint, TransportKind::WebSocket, EncodingKind::Json);
+
+    let err = client
+        .create("counter", vec![], CreateOptions::default())
+        .await
+        .err()
+        .unwrap();
+
+    let Error::Manager(message) = err else {
+        panic!("expected manager error, got {:?}", err);
+    };
+    assert!(message.contains("502"), "{}", message);
+}
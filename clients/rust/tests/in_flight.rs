@@ -0,0 +1,108 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{init_msg, recv_json, send_json, serve_ws_actor, spawn_action, ActorSocket};
+use rivetkit_client::{
+    connection::ActorConnection, Client, EncodingKind, Error, GetOptions, InFlightPolicy,
+    OfflineQueuePolicy, TransportKind,
+};
+use serde_json::{json, Value};
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::task::JoinHandle;
+
+async fn connect(
+    policy: InFlightPolicy,
+    offline_queue: OfflineQueuePolicy,
+) -> (Client, ActorConnection, UnboundedReceiver<ActorSocket>, ActorSocket) {
+    let (endpoint, mut sockets) = serve_ws_actor().await;
+
+    let client = Client::builder()
+        .endpoint(&endpoint)
+        .transport(TransportKind::WebSocket)
+        .encoding(EncodingKind::Json)
+        .in_flight_policy(policy)
+        .offline_queue(offline_queue)
+        .build()
+        .unwrap();
+    let conn = client
+        .get_for_id("actor-123", GetOptions::default())
+        .unwrap()
+        .connect();
+
+    let mut ws = sockets.recv().await.unwrap();
+    send_json(&mut ws, init_msg("actor-123", "conn-1")).await;
+    conn.ready().await.unwrap();
+
+    (client, conn, sockets, ws)
+}
+
+async fn finish(action: JoinHandle<Result<Value, Error>>) -> Result<Value, Error> {
+    tokio::time::timeout(Duration::from_secs(5), action)
+        .await
+        .expect("action hung after the connection dropped")
+        .unwrap()
+}
+
+#[tokio::test]
+async fn fail_policy_fails_sent_actions() {
+    let (_client, conn, _sockets, mut ws) = connect(InFlightPolicy::Fail, OfflineQueuePolicy::default()).await;
+
+    let action = spawn_action(&conn, "increment", true);
+    recv_json(&mut ws).await.unwrap();
+
+    // Kill the socket before answering
+    drop(ws);
+
+    let res = finish(action).await;
+    assert!(matches!(res, Err(Error::ConnectionLost)), "{:?}", res);
+}
+
+#[tokio::test]
+async fn resend_policy_retries_idempotent_actions() {
+    let (_client, conn, mut sockets, mut ws) = connect(InFlightPolicy::ResendIdempotent, OfflineQueuePolicy::default()).await;
+
+    let idempotent = spawn_action(&conn, "getCount", true);
+    let other = spawn_action(&conn, "increment", false);
+    recv_json(&mut ws).await.unwrap();
+    recv_json(&mut ws).await.unwrap();
+
+    drop(ws);
+
+    let res = finish(other).await;
+    assert!(matches!(res, Err(Error::ConnectionLost)), "{:?}", res);
+
+    let mut ws = sockets.recv().await.unwrap();
+    send_json(&mut ws, init_msg("actor-123", "conn-2")).await;
+
+    let req = recv_json(&mut ws).await.unwrap();
+    assert_eq!(req["b"]["ar"]["n"], json!("getCount"));
+    send_json(&mut ws, json!({ "b": { "ar": { "i": req["b"]["ar"]["i"], "o": 7 } } })).await;
+
+    assert_eq!(finish(idempotent).await.unwrap(), json!(7));
+}
+
+#[tokio::test]
+async fn resend_policy_bypasses_offline_queue_policy() {
+    // Actions lost on reconnect are not new offline actions, so even a
+    // disabled queue keeps them
+    let (_client, conn, mut sockets, mut ws) =
+        connect(InFlightPolicy::ResendIdempotent, OfflineQueuePolicy::disabled()).await;
+
+    let action = spawn_action(&conn, "getCount", true);
+    recv_json(&mut ws).await.unwrap();
+
+    drop(ws);
+
+    let mut ws = sockets.recv().await.unwrap();
+    send_json(&mut ws, init_msg("actor-123", "conn-2")).await;
+
+    let req = tokio::time::timeout(Duration::from_secs(5), recv_json(&mut ws))
+        .await
+        .expect("action was not re-sent")
+        .unwrap();
+    assert_eq!(req["b"]["ar"]["n"], json!("getCount"));
+    send_json(&mut ws, json!({ "b": { "ar": { "i": req["b"]["ar"]["i"], "o": 7 } } })).await;
+
+    assert_eq!(finish(action).await.unwrap(), json!(7));
+}
@@ -0,0 +1,157 @@
+#![cfg(feature = "metrics")]
+
+mod common;
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use common::{init_msg, recv_json, send_json, serve_once_with_request, serve_ws_actor};
+use futures_util::SinkExt;
+use metrics_util::debugging::{DebugValue, DebuggingRecorder, Snapshotter};
+use rivetkit_client::{Client, EncodingKind, GetOptions, TransportKind};
+use serde_json::json;
+use tokio_tungstenite::tungstenite::Message;
+
+/// Running totals, snapshots reset the recorder's values.
+struct Recorded {
+    snapshotter: Snapshotter,
+    counters: HashMap<String, u64>,
+    gauges: HashMap<String, f64>,
+    histograms: HashMap<String, Vec<f64>>,
+}
+
+impl Recorded {
+    fn refresh(&mut self) {
+        for (key, _, _, value) in self.snapshotter.snapshot().into_vec() {
+            let key = key.key();
+            let mut labels: Vec<_> = key.labels().map(|l| format!("{}={}", l.key(), l.value())).collect();
+            labels.sort();
+            let name = format!("{}{{{}}}", key.name(), labels.join(","));
+
+            match value {
+                DebugValue::Counter(n) => *self.counters.entry(name).or_default() += n,
+                DebugValue::Gauge(n) => *self.gauges.entry(name).or_default() += n.into_inner(),
+                DebugValue::Histogram(xs) => self
+                    .histograms
+                    .entry(name)
+                    .or_default()
+                    .extend(xs.into_iter().map(|x| x.into_inner())),
+            }
+        }
+    }
+
+    fn counter(&mut self, name: &str) -> u64 {
+        self.refresh();
+        self.counters.get(name).copied().unwrap_or_default()
+    }
+
+    fn gauge(&mut self, name: &str) -> f64 {
+        self.refresh();
+        self.gauges.get(name).copied().unwrap_or_default()
+    }
+
+    fn samples(&mut self, name: &str) -> usize {
+        self.refresh();
+        self.histograms.get(name).map_or(0, |xs| xs.len())
+    }
+
+    /// Polls until `check` holds, the drivers record from their own tasks.
+    async fn wait_for(&mut self, mut check: impl FnMut(&mut Self) -> bool) {
+        for _ in 0..100 {
+            if check(self) {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        panic!("metrics did not reach the expected values: {:?}", self.counters);
+    }
+}
+
+#[tokio::test]
+async fn records_client_metrics() {
+    let recorder = DebuggingRecorder::new();
+    let mut metrics = Recorded {
+        snapshotter: recorder.snapshotter(),
+        counters: HashMap::new(),
+        gauges: HashMap::new(),
+        histograms: HashMap::new(),
+    };
+    recorder.install().unwrap();
+
+    // Stateless action
+    let body = serde_json::to_vec(&json!({ "o": 1 })).unwrap();
+    let (endpoint, _req) = serve_once_with_request("200 OK", "application/json", body).await;
+    let client = Client::new(&endpoint, TransportKind::WebSocket, EncodingKind::Json);
+    let handle = client.get_for_id("actor-123", GetOptions::default()).unwrap();
+    handle.action("getCount", vec![]).await.unwrap();
+
+    assert_eq!(
+        metrics.samples("rivetkit_client_action_duration_seconds{action=getCount,transport=http}"),
+        1
+    );
+    assert_eq!(metrics.counter("rivetkit_client_messages_sent_total{encoding=json}"), 1);
+    assert_eq!(metrics.counter("rivetkit_client_messages_received_total{encoding=json}"), 1);
+    assert_eq!(metrics.counter("rivetkit_client_bytes_received_total{encoding=json}"), 7);
+
+    // Connection action
+    let (endpoint, mut sockets) = serve_ws_actor().await;
+    let client = Client::new(&endpoint, TransportKind::WebSocket, EncodingKind::Json);
+    let conn = client
+        .get_for_id("actor-123", GetOptions::default())
+        .unwrap()
+        .connect();
+    let mut ws = sockets.recv().await.unwrap();
+    send_json(&mut ws, init_msg("actor-123", "conn-1")).await;
+    conn.ready().await.unwrap();
+
+    let action = tokio::spawn({
+        let conn = conn.clone();
+        async move { conn.action("increment", vec![]).await }
+    });
+    let req = recv_json(&mut ws).await.unwrap();
+    assert_eq!(metrics.gauge("rivetkit_client_actions_in_flight{}"), 1.0);
+
+    send_json(&mut ws, json!({ "b": { "ar": { "i": req["b"]["ar"]["i"], "o": 2 } } })).await;
+    action.await.unwrap().unwrap();
+    assert_eq!(metrics.gauge("rivetkit_client_actions_in_flight{}"), 0.0);
+    assert_eq!(
+        metrics.samples("rivetkit_client_action_duration_seconds{action=increment,transport=websocket}"),
+        1
+    );
+
+    // Malformed messages are counted and skipped
+    ws.send(Message::Text("not json".into())).await.unwrap();
+    metrics
+        .wait_for(|m| m.counter("rivetkit_client_deserialization_failures_total{encoding=json}") == 1)
+        .await;
+
+    // Server disconnects count as reconnects
+    ws.close(None).await.unwrap();
+    metrics
+        .wait_for(|m| m.counter("rivetkit_client_reconnects_total{reason=server_disconnect}") == 1)
+        .await;
+    conn.disconnect().await;
+
+    // Actions queue while no server is reachable
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    drop(listener);
+
+    let client = Client::new(&endpoint, TransportKind::WebSocket, EncodingKind::Json);
+    let conn = client
+        .get_for_id("actor-123", GetOptions::default())
+        .unwrap()
+        .connect();
+    let queued = tokio::spawn({
+        let conn = conn.clone();
+        async move { conn.action("queued", vec![]).await }
+    });
+    metrics.wait_for(|m| m.gauge("rivetkit_client_queue_depth{}") == 1.0).await;
+
+    // Dropping the connection releases its queue
+    conn.disconnect().await;
+    queued.await.unwrap().unwrap_err();
+    drop(conn);
+    drop(client);
+    metrics.wait_for(|m| m.gauge("rivetkit_client_queue_depth{}") == 0.0).await;
+}
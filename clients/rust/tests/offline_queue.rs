@@ -0,0 +1,163 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{closed_endpoint, init_msg, recv_json, send_json, serve_ws_actor, spawn_action, ActorSocket};
+use rivetkit_client::{
+    connection::ActorConnection, ActionOptions, Client, EncodingKind, Error, GetOptions,
+    OfflineQueuePolicy, QueueOverflow, ReconnectPolicy, TransportKind,
+};
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+fn client(endpoint: &str, offline_queue: OfflineQueuePolicy) -> Client {
+    Client::builder()
+        .endpoint(endpoint)
+        .transport(TransportKind::WebSocket)
+        .encoding(EncodingKind::Json)
+        .offline_queue(offline_queue)
+        .reconnect_policy(ReconnectPolicy {
+            initial_delay: Duration::from_millis(200),
+            max_delay: Duration::from_millis(200),
+            ..Default::default()
+        })
+        .build()
+        .unwrap()
+}
+
+async fn queued_conn(offline_queue: OfflineQueuePolicy) -> (Client, ActorConnection) {
+    let client = client(&closed_endpoint().await, offline_queue);
+    let conn = client
+        .get_for_id("actor-123", GetOptions::default())
+        .unwrap()
+        .connect();
+
+    (client, conn)
+}
+
+async fn is_pending(action: &JoinHandle<Result<Value, Error>>) -> bool {
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    !action.is_finished()
+}
+
+#[tokio::test]
+async fn disabled_queue_fails_immediately() {
+    let (_client, conn) = queued_conn(OfflineQueuePolicy::disabled()).await;
+
+    let res = conn.action("increment", vec![]).await;
+    assert!(matches!(res, Err(Error::NotConnected)), "{:?}", res);
+}
+
+#[tokio::test]
+async fn overflow_policies() {
+    let policy = |overflow| OfflineQueuePolicy {
+        max_len: 1,
+        overflow,
+        ..Default::default()
+    };
+
+    let (_client, conn) = queued_conn(policy(QueueOverflow::Reject)).await;
+    let first = spawn_action(&conn, "first", false);
+    assert!(is_pending(&first).await);
+    let res = conn.action("second", vec![]).await;
+    assert!(matches!(res, Err(Error::QueueFull)), "{:?}", res);
+    assert!(is_pending(&first).await);
+
+    let (_client, conn) = queued_conn(policy(QueueOverflow::DropOldest)).await;
+    let first = spawn_action(&conn, "first", false);
+    assert!(is_pending(&first).await);
+    let second = spawn_action(&conn, "second", false);
+    assert!(matches!(first.await.unwrap(), Err(Error::QueueFull)));
+    assert!(is_pending(&second).await);
+
+    let (_client, conn) = queued_conn(policy(QueueOverflow::FailFast)).await;
+    let first = spawn_action(&conn, "first", false);
+    assert!(is_pending(&first).await);
+    let res = conn.action("second", vec![]).await;
+    assert!(matches!(res, Err(Error::QueueFull)), "{:?}", res);
+    assert!(matches!(first.await.unwrap(), Err(Error::QueueFull)));
+}
+
+#[tokio::test]
+async fn expired_messages_fail() {
+    let (_client, conn) = queued_conn(OfflineQueuePolicy {
+        max_age: Some(Duration::from_millis(50)),
+        ..Default::default()
+    })
+    .await;
+
+    let first = spawn_action(&conn, "first", false);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let second = spawn_action(&conn, "second", false);
+
+    assert!(matches!(first.await.unwrap(), Err(Error::QueueExpired)));
+    assert!(is_pending(&second).await);
+}
+
+/// Like [`serve_ws_actor`], but each handshake waits for a permit from the
+/// returned sender so the test controls when the connection comes back.
+async fn serve_gated() -> (String, mpsc::UnboundedSender<()>, mpsc::UnboundedReceiver<ActorSocket>) {
+    let (endpoint, mut sockets) = serve_ws_actor().await;
+    let proxy = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let proxy_endpoint = format!("http://{}", proxy.local_addr().unwrap());
+    let upstream = endpoint.trim_start_matches("http://").to_string();
+
+    let (gate_tx, mut gate_rx) = mpsc::unbounded_channel::<()>();
+    let (tx, rx) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        while let Ok((mut client, _)) = proxy.accept().await {
+            if gate_rx.recv().await.is_none() {
+                break;
+            }
+            let mut server = tokio::net::TcpStream::connect(&upstream).await.unwrap();
+            tokio::spawn(async move {
+                tokio::io::copy_bidirectional(&mut client, &mut server).await.ok();
+            });
+            tx.send(sockets.recv().await.unwrap()).unwrap();
+        }
+    });
+
+    (proxy_endpoint, gate_tx, rx)
+}
+
+#[tokio::test]
+async fn reconnect_skips_abandoned_actions() {
+    let (endpoint, gate, mut sockets) = serve_gated().await;
+    let client = client(&endpoint, OfflineQueuePolicy::default());
+    let conn = client
+        .get_for_id("actor-123", GetOptions::default())
+        .unwrap()
+        .connect();
+
+    gate.send(()).unwrap();
+    let mut ws = sockets.recv().await.unwrap();
+    send_json(&mut ws, init_msg("actor-123", "conn-1")).await;
+    conn.ready().await.unwrap();
+
+    drop(ws);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    // Queued while reconnecting
+    let res = conn
+        .action_with_options(
+            "abandoned",
+            vec![],
+            ActionOptions {
+                timeout: Some(Duration::from_millis(20)),
+                ..Default::default()
+            },
+        )
+        .await;
+    assert!(matches!(res, Err(Error::Timeout)), "{:?}", res);
+    let _kept = spawn_action(&conn, "kept", false);
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    gate.send(()).unwrap();
+    let mut ws = sockets.recv().await.unwrap();
+    send_json(&mut ws, init_msg("actor-123", "conn-2")).await;
+
+    let req = recv_json(&mut ws).await.unwrap();
+    assert_eq!(req["b"]["ar"]["n"], json!("kept"));
+}
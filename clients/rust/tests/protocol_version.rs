@@ -0,0 +1,159 @@
+mod common;
+
+use std::time::Duration;
+
+use common::serve_once_with_request;
+use rivetkit_client::{Client, EncodingKind, GetOptions, ProtocolVersion, TransportKind};
+use serde_json::json;
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http::{HeaderValue, StatusCode};
+
+async fn action_request_head(version: ProtocolVersion) -> String {
+    let body = serde_json::to_vec(&json!({ "o": 1 })).unwrap();
+    let (endpoint, req_rx) = serve_once_with_request("200 OK", "application/json", body).await;
+
+    let client = Client::builder()
+        .endpoint(&endpoint)
+        .encoding(EncodingKind::Json)
+        .protocol_version(version)
+        .build()
+        .unwrap();
+    let handle = client.get_for_id("actor-123", GetOptions::default()).unwrap();
+    handle.action("increment", vec![]).await.unwrap();
+
+    req_rx.await.unwrap().to_lowercase()
+}
+
+#[tokio::test]
+async fn current_protocol_sends_rivetkit_headers() {
+    let head = action_request_head(ProtocolVersion::Current).await;
+
+    assert!(head.contains("x-rivetkit-query:"), "{}", head);
+    assert!(head.contains("x-rivetkit-encoding: json"), "{}", head);
+    assert!(!head.contains("x-ac-"), "{}", head);
+}
+
+#[tokio::test]
+async fn default_protocol_is_current() {
+    let body = serde_json::to_vec(&json!({ "o": 1 })).unwrap();
+    let (endpoint, req_rx) = serve_once_with_request("200 OK", "application/json", body).await;
+
+    let client = Client::new(&endpoint, TransportKind::Sse, EncodingKind::Json);
+    assert_eq!(client.config().effective_protocol(), ProtocolVersion::Current);
+
+    let handle = client.get_for_id("actor-123", GetOptions::default()).unwrap();
+    handle.action("increment", vec![]).await.unwrap();
+
+    let head = req_rx.await.unwrap().to_lowercase();
+    assert!(head.contains("x-rivetkit-query:"), "{}", head);
+    assert!(!head.contains("x-ac-"), "{}", head);
+}
+
+#[tokio::test]
+async fn legacy_protocol_sends_ac_headers() {
+    let head = action_request_head(ProtocolVersion::Legacy).await;
+
+    assert!(head.contains("x-ac-query:"), "{}", head);
+    assert!(head.contains("x-ac-encoding: json"), "{}", head);
+    assert!(!head.contains("x-rivetkit-"), "{}", head);
+}
+
+/// Accepts WebSocket upgrades, reporting each request's URI & subprotocols.
+/// Upgrades without a query string are rejected with a 404 when
+/// `legacy_only` is set.
+#[allow(clippy::result_large_err)]
+async fn serve_ws(legacy_only: bool) -> (String, mpsc::UnboundedReceiver<(String, Option<String>)>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    let (tx, rx) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        let mut sockets = Vec::new();
+        while let Ok((socket, _)) = listener.accept().await {
+            let tx = tx.clone();
+            let callback = move |req: &Request, mut res: Response| -> Result<Response, ErrorResponse> {
+                let protocols = req
+                    .headers()
+                    .get("sec-websocket-protocol")
+                    .map(|v| v.to_str().unwrap().to_string());
+                let _ = tx.send((req.uri().to_string(), protocols.clone()));
+
+                if legacy_only && req.uri().query().is_none() {
+                    let mut err = ErrorResponse::new(None);
+                    *err.status_mut() = StatusCode::NOT_FOUND;
+                    return Err(err);
+                }
+                if protocols.is_some() {
+                    res.headers_mut()
+                        .insert("sec-websocket-protocol", HeaderValue::from_static("rivetkit"));
+                }
+                Ok(res)
+            };
+            if let Ok(ws) = tokio_tungstenite::accept_hdr_async(socket, callback).await {
+                sockets.push(ws);
+            }
+        }
+    });
+
+    (endpoint, rx)
+}
+
+async fn wait_for_protocol(client: &Client) -> ProtocolVersion {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            match client.config().effective_protocol() {
+                ProtocolVersion::Auto => tokio::time::sleep(Duration::from_millis(10)).await,
+                version => return version,
+            }
+        }
+    })
+    .await
+    .expect("protocol was not detected")
+}
+
+fn auto_client(endpoint: &str) -> Client {
+    Client::builder()
+        .endpoint(endpoint)
+        .transport(TransportKind::WebSocket)
+        .encoding(EncodingKind::Json)
+        .protocol_version(ProtocolVersion::Auto)
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn auto_protocol_uses_subprotocols() {
+    let (endpoint, mut requests) = serve_ws(false).await;
+
+    let client = auto_client(&endpoint);
+    let handle = client.get_for_id("actor-123", GetOptions::default()).unwrap();
+    let _conn = handle.connect();
+
+    assert_eq!(wait_for_protocol(&client).await, ProtocolVersion::Current);
+
+    let (uri, protocols) = requests.recv().await.unwrap();
+    assert_eq!(uri, "/actors/connect/websocket");
+    let protocols = protocols.unwrap();
+    assert!(protocols.contains("rivetkit"), "{}", protocols);
+    assert!(protocols.contains("encoding.json"), "{}", protocols);
+    assert!(protocols.contains("query.%7B"), "{}", protocols);
+}
+
+#[tokio::test]
+async fn auto_protocol_falls_back_to_legacy() {
+    let (endpoint, mut requests) = serve_ws(true).await;
+
+    let client = auto_client(&endpoint);
+    let handle = client.get_for_id("actor-123", GetOptions::default()).unwrap();
+    let _conn = handle.connect();
+
+    assert_eq!(wait_for_protocol(&client).await, ProtocolVersion::Legacy);
+
+    let (uri, _) = requests.recv().await.unwrap();
+    assert_eq!(uri, "/actors/connect/websocket");
+    let (uri, protocols) = requests.recv().await.unwrap();
+    assert!(uri.starts_with("/actors/connect/websocket?encoding=json&query="), "{}", uri);
+    assert!(protocols.is_none());
+}
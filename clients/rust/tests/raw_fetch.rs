@@ -0,0 +1,72 @@
+mod common;
+
+use common::serve_once_with_request;
+use futures_util::StreamExt;
+use rivetkit_client::{Client, EncodingKind, GetOptions, Method, RawRequest, TransportKind};
+use serde_json::json;
+
+#[tokio::test]
+async fn fetch_forwards_query_and_streams_body() {
+    let (endpoint, req_rx) =
+        serve_once_with_request("200 OK", "text/plain", b"file contents".to_vec()).await;
+
+    let client = Client::new(&endpoint, TransportKind::WebSocket, EncodingKind::Cbor);
+    let handle = client
+        .get_for_id(
+            "actor-123",
+            GetOptions {
+                params: Some(json!({ "token": "abc" })),
+            },
+        )
+        .unwrap();
+
+    let chunks = futures_util::stream::iter(vec![
+        Ok::<_, std::io::Error>(b"hello ".to_vec()),
+        Ok(b"world".to_vec()),
+    ]);
+    let res = handle
+        .fetch(
+            "/files/a.txt?download=1",
+            RawRequest::new(Method::PUT)
+                .header("X-Custom", "1")
+                .body_stream(chunks),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["content-type"], "text/plain");
+
+    let mut body = Vec::new();
+    let mut stream = res.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        body.extend_from_slice(&chunk.unwrap());
+    }
+    assert_eq!(body, b"file contents");
+
+    let head = req_rx.await.unwrap();
+    let head_lower = head.to_lowercase();
+    assert!(head.starts_with("PUT /actors/raw/http/files/a.txt?download=1 "), "{}", head);
+    assert!(head_lower.contains("x-rivetkit-query:"), "{}", head);
+    assert!(head_lower.contains("x-rivetkit-encoding: json"), "{}", head);
+    assert!(head_lower.contains("x-rivetkit-conn-params:"), "{}", head);
+    assert!(head_lower.contains("x-custom: 1"), "{}", head);
+    assert!(head_lower.contains("transfer-encoding: chunked"), "{}", head);
+}
+
+#[tokio::test]
+async fn fetch_returns_error_statuses() {
+    let (endpoint, _req_rx) =
+        serve_once_with_request("404 Not Found", "text/plain", b"missing".to_vec()).await;
+
+    let client = Client::new(&endpoint, TransportKind::WebSocket, EncodingKind::Json);
+    let handle = client.get_for_id("actor-123", GetOptions::default()).unwrap();
+
+    let res = handle
+        .fetch("missing", RawRequest::new(Method::GET))
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), 404);
+    assert_eq!(res.text().await.unwrap(), "missing");
+}
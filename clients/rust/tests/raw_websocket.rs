@@ -0,0 +1,68 @@
+use futures_util::{SinkExt, StreamExt};
+use rivetkit_client::{Client, EncodingKind, GetOptions, RawMessage, TransportKind};
+use serde_json::json;
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http::HeaderValue;
+
+/// Echoes every data frame back, reporting the upgrade URI & subprotocols.
+#[allow(clippy::result_large_err)]
+async fn serve_echo() -> (String, oneshot::Receiver<(String, String)>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let endpoint = format!("http://{}", listener.local_addr().unwrap());
+    let (tx, rx) = oneshot::channel();
+
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let callback = move |req: &Request, mut res: Response| -> Result<Response, ErrorResponse> {
+            let protocols = req.headers()["sec-websocket-protocol"].to_str().unwrap().to_string();
+            let _ = tx.send((req.uri().to_string(), protocols));
+            res.headers_mut()
+                .insert("sec-websocket-protocol", HeaderValue::from_static("game.v1"));
+            Ok(res)
+        };
+        let mut ws = tokio_tungstenite::accept_hdr_async(socket, callback).await.unwrap();
+
+        while let Some(Ok(msg)) = ws.next().await {
+            if msg.is_text() || msg.is_binary() {
+                ws.send(msg).await.unwrap();
+            }
+        }
+    });
+
+    (endpoint, rx)
+}
+
+#[tokio::test]
+async fn raw_websocket_round_trips_frames() {
+    let (endpoint, req_rx) = serve_echo().await;
+
+    let client = Client::new(&endpoint, TransportKind::WebSocket, EncodingKind::Cbor);
+    let handle = client
+        .get_for_id(
+            "actor-123",
+            GetOptions {
+                params: Some(json!({ "token": "abc" })),
+            },
+        )
+        .unwrap();
+
+    let mut ws = handle.raw_websocket("/game", &["game.v1"]).await.unwrap();
+    assert_eq!(ws.protocol(), Some("game.v1"));
+
+    ws.send(RawMessage::Text("hello".into())).await.unwrap();
+    ws.send(RawMessage::Binary(vec![1, 2, 3].into())).await.unwrap();
+
+    assert_eq!(ws.next().await.unwrap().unwrap(), RawMessage::Text("hello".into()));
+    assert_eq!(ws.next().await.unwrap().unwrap(), RawMessage::Binary(vec![1, 2, 3].into()));
+
+    ws.close().await.unwrap();
+
+    let (uri, protocols) = req_rx.await.unwrap();
+    assert_eq!(uri, "/actors/raw/websocket/game");
+    assert!(protocols.contains("query.%7B"), "{}", protocols);
+    assert!(protocols.contains("encoding.json"), "{}", protocols);
+    assert!(protocols.contains("conn_params.%7B"), "{}", protocols);
+    assert!(protocols.ends_with("game.v1"), "{}", protocols);
+}
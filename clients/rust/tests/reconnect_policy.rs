@@ -0,0 +1,159 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use common::{closed_endpoint, init_msg, send_json, serve_ws_actor, wait_for_failure};
+use rivetkit_client::{
+    connection::ActorConnection, Client, ConnectionError, ConnectionStatus, EncodingKind, Error,
+    GetOptions, Jitter, ReconnectPolicy, RetryDecision, TransportKind,
+};
+
+fn connect(endpoint: &str, policy: ReconnectPolicy) -> (Client, ActorConnection) {
+    let client = Client::builder()
+        .endpoint(endpoint)
+        .transport(TransportKind::WebSocket)
+        .encoding(EncodingKind::Json)
+        .reconnect_policy(policy)
+        .build()
+        .unwrap();
+    let conn = client
+        .get_for_id("actor-123", GetOptions::default())
+        .unwrap()
+        .connect();
+
+    (client, conn)
+}
+
+#[tokio::test]
+async fn gives_up_after_max_attempts() {
+    let (_client, conn) = connect(
+        &closed_endpoint().await,
+        ReconnectPolicy {
+            initial_delay: Duration::from_millis(10),
+            max_attempts: Some(2),
+            ..Default::default()
+        },
+    );
+
+    let action = tokio::spawn({
+        let conn = conn.clone();
+        async move { conn.action("increment", vec![]).await }
+    });
+
+    assert_eq!(
+        wait_for_failure(&conn).await,
+        ConnectionError::RetriesExhausted { attempts: 2 }
+    );
+    let res = action.await.unwrap();
+    assert!(matches!(res, Err(Error::RetriesExhausted { attempts: 2 })), "{:?}", res);
+}
+
+#[tokio::test]
+async fn hook_adjusts_and_vetoes_retries() {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let (_client, conn) = connect(
+        &closed_endpoint().await,
+        ReconnectPolicy {
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(1),
+            jitter: Jitter::Full,
+            ..Default::default()
+        }
+        .on_retry({
+            let seen = seen.clone();
+            move |ctx| {
+                seen.lock().unwrap().push((ctx.attempt, ctx.delay));
+                if ctx.attempt > 4 {
+                    RetryDecision::GiveUp
+                } else {
+                    RetryDecision::RetryAfter(Duration::from_millis(5))
+                }
+            }
+        }),
+    );
+
+    assert_eq!(
+        wait_for_failure(&conn).await,
+        ConnectionError::RetriesExhausted { attempts: 4 }
+    );
+
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.iter().map(|(attempt, _)| *attempt).collect::<Vec<_>>(), [1, 2, 3, 4, 5]);
+    // Full jitter stays below the exponential delay
+    for (i, (_, delay)) in seen.iter().enumerate() {
+        assert!(*delay <= Duration::from_millis(100 << i).min(Duration::from_secs(1)), "{:?}", seen);
+    }
+}
+
+#[tokio::test]
+async fn backoff_only_resets_after_healthy_connection() {
+    let (endpoint, mut sockets) = serve_ws_actor().await;
+    let (_client, conn) = connect(
+        &endpoint,
+        ReconnectPolicy {
+            initial_delay: Duration::from_millis(50),
+            healthy_after: Duration::from_secs(3600),
+            ..Default::default()
+        },
+    );
+
+    let mut ws = sockets.recv().await.unwrap();
+    send_json(&mut ws, init_msg("actor-123", "conn-1")).await;
+    conn.ready().await.unwrap();
+
+    let mut status = conn.status();
+    drop(ws);
+
+    // The connection dropped too soon, so it counts as a failed attempt
+    let status = tokio::time::timeout(
+        Duration::from_secs(5),
+        status.wait_for(|s| matches!(s, ConnectionStatus::Reconnecting { .. })),
+    )
+    .await
+    .unwrap()
+    .unwrap()
+    .clone();
+    assert_eq!(
+        status,
+        ConnectionStatus::Reconnecting {
+            attempt: 1,
+            next_delay: Duration::from_millis(50),
+        }
+    );
+}
+
+#[tokio::test]
+async fn reconnect_after_healthy_connection_is_jittered() {
+    let (endpoint, mut sockets) = serve_ws_actor().await;
+    let (_client, conn) = connect(
+        &endpoint,
+        ReconnectPolicy {
+            initial_delay: Duration::from_secs(60),
+            jitter: Jitter::Full,
+            ..Default::default()
+        },
+    );
+
+    let mut ws = sockets.recv().await.unwrap();
+    send_json(&mut ws, init_msg("actor-123", "conn-1")).await;
+    conn.ready().await.unwrap();
+
+    let mut status = conn.status();
+    drop(ws);
+
+    let status = tokio::time::timeout(
+        Duration::from_secs(5),
+        status.wait_for(|s| matches!(s, ConnectionStatus::Reconnecting { .. })),
+    )
+    .await
+    .unwrap()
+    .unwrap()
+    .clone();
+    let ConnectionStatus::Reconnecting { attempt, next_delay } = status else {
+        unreachable!();
+    };
+    assert_eq!(attempt, 1);
+    // Drawn from [0, initial_delay] rather than reconnecting right away
+    assert!(next_delay > Duration::ZERO && next_delay < Duration::from_secs(60), "{:?}", next_delay);
+}
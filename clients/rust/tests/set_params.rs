@@ -0,0 +1,116 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{init_msg, recv_json, send_json, serve_ws_actor_with_protocols};
+use rivetkit_client::{
+    Client, ConnectionStatus, EncodingKind, GetOptions, ReconnectPolicy, RetryDecision, TransportKind,
+};
+use serde_json::json;
+
+fn params_protocol(token: &str) -> String {
+    let params = json!({ "token": token }).to_string();
+    format!("conn_params.{}", urlencoding::encode(&params))
+}
+
+#[tokio::test]
+async fn set_params_reconnects_and_keeps_state() {
+    let (endpoint, mut requests) = serve_ws_actor_with_protocols().await;
+
+    let client = Client::new(&endpoint, TransportKind::WebSocket, EncodingKind::Json);
+    let conn = client
+        .get_for_id(
+            "actor-123",
+            GetOptions {
+                params: Some(json!({ "token": "old" })),
+            },
+        )
+        .unwrap()
+        .connect();
+
+    let (protocols, mut ws) = requests.recv().await.unwrap();
+    assert!(protocols.contains(&params_protocol("old")), "{:?}", protocols);
+    send_json(&mut ws, init_msg("actor-123", "conn-1")).await;
+    conn.ready().await.unwrap();
+
+    let _sub = conn.on_event("newCount", |_| {}).await;
+    recv_json(&mut ws).await.unwrap();
+
+    conn.set_params(Some(json!({ "token": "new" }))).await;
+
+    // The old socket is closed and the next handshake uses the new params
+    while recv_json(&mut ws).await.is_some() {}
+    let action = tokio::spawn({
+        let conn = conn.clone();
+        async move { conn.action("getCount", vec![]).await }
+    });
+
+    let (protocols, mut ws) = requests.recv().await.unwrap();
+    assert!(protocols.contains(&params_protocol("new")), "{:?}", protocols);
+    send_json(&mut ws, init_msg("actor-123", "conn-2")).await;
+
+    assert_eq!(
+        recv_json(&mut ws).await.unwrap(),
+        json!({ "b": { "sr": { "e": "newCount", "s": true } } })
+    );
+    let req = recv_json(&mut ws).await.unwrap();
+    assert_eq!(req["b"]["ar"]["n"], json!("getCount"));
+    send_json(&mut ws, json!({ "b": { "ar": { "i": req["b"]["ar"]["i"], "o": 3 } } })).await;
+    assert_eq!(action.await.unwrap().unwrap(), json!(3));
+
+    let info = conn.ready().await.unwrap();
+    assert_eq!(info.connection_id, "conn-2");
+}
+
+#[tokio::test]
+async fn set_params_while_reconnecting_connects_once() {
+    let (endpoint, mut requests) = serve_ws_actor_with_protocols().await;
+
+    let client = Client::builder()
+        .endpoint(&endpoint)
+        .transport(TransportKind::WebSocket)
+        .encoding(EncodingKind::Json)
+        .reconnect_policy(
+            ReconnectPolicy::default().on_retry(|_| RetryDecision::RetryAfter(Duration::from_secs(60))),
+        )
+        .build()
+        .unwrap();
+    let conn = client
+        .get_for_id(
+            "actor-123",
+            GetOptions {
+                params: Some(json!({ "token": "old" })),
+            },
+        )
+        .unwrap()
+        .connect();
+
+    let (_, mut ws) = requests.recv().await.unwrap();
+    send_json(&mut ws, init_msg("actor-123", "conn-1")).await;
+    conn.ready().await.unwrap();
+
+    drop(ws);
+    let mut status = conn.status();
+    status
+        .wait_for(|s| matches!(s, ConnectionStatus::Reconnecting { .. }))
+        .await
+        .unwrap();
+
+    // Cuts the delay short, connecting once with the new params
+    conn.set_params(Some(json!({ "token": "new" }))).await;
+
+    let (protocols, mut ws) = requests.recv().await.unwrap();
+    assert!(protocols.contains(&params_protocol("new")), "{:?}", protocols);
+    send_json(&mut ws, init_msg("actor-123", "conn-2")).await;
+
+    let info = tokio::time::timeout(Duration::from_secs(5), conn.ready())
+        .await
+        .expect("connection was replaced")
+        .unwrap();
+    assert_eq!(info.connection_id, "conn-2");
+    assert!(
+        tokio::time::timeout(Duration::from_millis(300), requests.recv()).await.is_err(),
+        "connection was replaced"
+    );
+    assert!(matches!(&*conn.status().borrow(), ConnectionStatus::Connected { .. }));
+}
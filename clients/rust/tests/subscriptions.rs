@@ -0,0 +1,59 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{connect_ws_actor, event_msg, recv_json, send_json, ActorSocket};
+use serde_json::json;
+use tokio::sync::mpsc;
+
+async fn next_json(ws: &mut ActorSocket) -> serde_json::Value {
+    tokio::time::timeout(Duration::from_secs(5), recv_json(ws))
+        .await
+        .expect("no message from client")
+        .unwrap()
+}
+
+#[tokio::test]
+async fn unsubscribes_once_last_listener_is_gone() {
+    let (_client, conn, mut ws) = connect_ws_actor().await;
+
+    let (tx, mut events) = mpsc::unbounded_channel();
+    let first = conn
+        .on_event("newCount", move |args| {
+            tx.send(args.to_vec()).unwrap();
+        })
+        .await;
+    assert_eq!(next_json(&mut ws).await, json!({ "b": { "sr": { "e": "newCount", "s": true } } }));
+
+    // A second listener shares the server-side subscription
+    let second = conn.on_event("newCount", |_| {}).await;
+
+    send_json(&mut ws, event_msg("newCount", json!([1]))).await;
+    assert_eq!(events.recv().await.unwrap(), vec![json!(1)]);
+
+    drop(first);
+    second.unsubscribe().await;
+    assert_eq!(next_json(&mut ws).await, json!({ "b": { "sr": { "e": "newCount", "s": false } } }));
+
+    // The dropped listener is no longer called
+    send_json(&mut ws, event_msg("newCount", json!([2]))).await;
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(events.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn once_resolves_with_next_event() {
+    let (_client, conn, mut ws) = connect_ws_actor().await;
+
+    let once = tokio::spawn({
+        let conn = conn.clone();
+        async move { conn.once("newCount").await }
+    });
+    assert_eq!(next_json(&mut ws).await, json!({ "b": { "sr": { "e": "newCount", "s": true } } }));
+
+    send_json(&mut ws, event_msg("newCount", json!([3, "x"]))).await;
+    assert_eq!(once.await.unwrap().unwrap(), vec![json!(3), json!("x")]);
+
+    // The one-shot listener removes its subscription
+    assert_eq!(next_json(&mut ws).await, json!({ "b": { "sr": { "e": "newCount", "s": false } } }));
+}
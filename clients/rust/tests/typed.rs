@@ -0,0 +1,39 @@
+mod common;
+
+use common::serve_once;
+use rivetkit_client::{Client, EncodingKind, Error, GetOptions, TransportKind};
+use serde_json::json;
+
+#[derive(Debug, serde::Deserialize, PartialEq)]
+struct Count {
+    count: i64,
+}
+
+#[tokio::test]
+async fn action_typed_deserializes_output() {
+    let body = serde_json::to_vec(&json!({ "o": { "count": 5 } })).unwrap();
+    let endpoint = serve_once("200 OK", "application/json", body).await;
+
+    let client = Client::new(&endpoint, TransportKind::WebSocket, EncodingKind::Json);
+    let handle = client.get_for_id("actor-123", GetOptions::default()).unwrap();
+
+    let out: Count = handle.action_typed("increment", (5,)).await.unwrap();
+
+    assert_eq!(out, Count { count: 5 });
+}
+
+#[tokio::test]
+async fn action_typed_reports_deserialization_error() {
+    let body = serde_json::to_vec(&json!({ "o": "not a count" })).unwrap();
+    let endpoint = serve_once("200 OK", "application/json", body).await;
+
+    let client = Client::new(&endpoint, TransportKind::WebSocket, EncodingKind::Json);
+    let handle = client.get_for_id("actor-123", GetOptions::default()).unwrap();
+
+    let err = handle
+        .action_typed::<_, Count>("increment", ())
+        .await
+        .unwrap_err();
+
+    assert!(matches!(err, Error::Deserialization(_)), "got {:?}", err);
+}